@@ -5,11 +5,18 @@
 // being recovered by walking siblings. Anything not represented here is insignificant whitespace.
 //
 // Text is borrowed from the source. Every piece of text the tree holds is a slice of the input,
-// which outlives the tree, so none of it is copied. Nothing rewrites a node's text -- the Cargo
-// conventions reorder nodes and adjust their blank-line flags, but the text itself is only ever
-// read -- so a plain `&str` is enough and a `Cow` would only make each node larger.
+// which outlives the tree, so none of it is copied. Sorting and the Cargo conventions only reorder
+// nodes and adjust their blank-line flags, so the one thing that does rewrite text is the downlevel
+// pass, which spells a TOML 1.1 escape or time the way TOML 1.0 can read it. That only happens to
+// keys and values, which is why those hold a `Cow` while comments keep a plain `&str`. Nodes also
+// record where they were written, so that one a `dprint-ignore` directive covers can be written
+// straight from the source instead.
+
+use std::borrow::Cow;
 
 use crate::configuration::Configuration;
+use crate::configuration::TomlVersion;
+use crate::parser::Span;
 
 /// A comment, from the `#` up to (but not including) the end of the line.
 #[derive(Debug, Clone)]
@@ -22,6 +29,17 @@ pub struct Comment<'a> {
   /// `maintain` indent settings go by. Zero for a comment that isn't on a line of its own at the
   /// top level, which is never indented independently.
   pub indent_in_source: usize,
+  /// Where the comment was written.
+  pub span: Span,
+  /// Whether the comment sits within a `# dprint-ignore-start` range, and so is written as it was.
+  pub ignored: bool,
+}
+
+impl Comment<'_> {
+  /// Whether the comment is the directive `name`, such as `# dprint-ignore`, however it is spaced.
+  pub fn is_directive(&self, name: &str) -> bool {
+    self.text.strip_prefix('#').is_some_and(|text| text.trim() == name)
+  }
 }
 
 /// A parsed TOML document.
@@ -48,9 +66,62 @@ impl RootItem<'_> {
     }
   }
 
+  pub fn set_blank_line_before(&mut self, value: bool) {
+    match self {
+      RootItem::Comment(c) => c.blank_line_before = value,
+      RootItem::Entry(e) => e.blank_line_before = value,
+      RootItem::TableHeader(h) => h.blank_line_before = value,
+    }
+  }
+
   pub fn is_table_header(&self) -> bool {
     matches!(self, RootItem::TableHeader(_))
   }
+
+  /// Whether the item is to be written exactly as it was, which also keeps it where it is.
+  pub fn is_ignored(&self) -> bool {
+    match self {
+      RootItem::Comment(c) => c.ignored,
+      RootItem::Entry(e) => e.ignored,
+      RootItem::TableHeader(h) => h.ignored,
+    }
+  }
+
+  pub fn set_ignored(&mut self, value: bool) {
+    match self {
+      RootItem::Comment(c) => c.ignored = value,
+      RootItem::Entry(e) => e.ignored = value,
+      RootItem::TableHeader(h) => h.ignored = value,
+    }
+  }
+
+  /// Where the item was written, from its first character to its last, leaving out any comment
+  /// beside it.
+  pub fn span(&self) -> Span {
+    match self {
+      RootItem::Comment(c) => c.span,
+      RootItem::Entry(e) => e.span(),
+      RootItem::TableHeader(h) => h.span,
+    }
+  }
+
+  /// How many whitespace characters preceded the item on its line.
+  pub fn indent_in_source(&self) -> usize {
+    match self {
+      RootItem::Comment(c) => c.indent_in_source,
+      RootItem::Entry(e) => e.indent_in_source,
+      RootItem::TableHeader(h) => h.indent_in_source,
+    }
+  }
+
+  /// The comment on the same line as the item.
+  pub fn trailing_comment(&self) -> Option<&Comment<'_>> {
+    match self {
+      RootItem::Comment(_) => None,
+      RootItem::Entry(e) => e.trailing_comment.as_ref(),
+      RootItem::TableHeader(h) => h.trailing_comment.as_ref(),
+    }
+  }
 }
 
 /// A table header, either `[key]` or `[[key]]`.
@@ -64,6 +135,10 @@ pub struct TableHeader<'a> {
   /// How many whitespace characters preceded the header on its line, which is what the
   /// `maintain` indent settings go by.
   pub indent_in_source: usize,
+  /// Where the header was written, from its opening bracket to its closing one.
+  pub span: Span,
+  /// Whether a `# dprint-ignore` directive or range asks for the header to be written as it was.
+  pub ignored: bool,
 }
 
 /// A key/value pair.
@@ -80,6 +155,16 @@ pub struct Entry<'a> {
   /// indent settings go by. Always zero for an entry inside an inline table, which is never
   /// indented on its own.
   pub indent_in_source: usize,
+  /// Whether a `# dprint-ignore` directive or range asks for the entry to be written as it was. Such
+  /// an entry is also left where it is, and nothing within it is changed.
+  pub ignored: bool,
+}
+
+impl Entry<'_> {
+  /// Where the entry was written, from the start of its key to the end of its value.
+  pub fn span(&self) -> Span {
+    Span::new(self.key.first.span.start, self.value.span.end)
+  }
 }
 
 /// A key, which may be dotted (`a.b.c`).
@@ -136,13 +221,24 @@ impl Key<'_> {
     }
     other_parts.next().is_some()
   }
+
+  /// Whether two keys name the same table, whatever their quoting.
+  pub fn names_same_table(&self, other: &Key<'_>) -> bool {
+    self.parts().count() == other.parts().count()
+      && self
+        .parts()
+        .zip(other.parts())
+        .all(|(part, other)| part.unquoted_text() == other.unquoted_text())
+  }
 }
 
 /// One dot separated segment of a key. Bare, quoted and literal keys are all kept verbatim, since
 /// the formatter reproduces the segment as written.
 #[derive(Debug, Clone)]
 pub struct KeyPart<'a> {
-  pub text: &'a str,
+  pub text: Cow<'a, str>,
+  /// Where the segment was written, which is what a semantic error about the key points at.
+  pub span: Span,
 }
 
 impl KeyPart<'_> {
@@ -150,12 +246,13 @@ impl KeyPart<'_> {
   /// escape within a basic string is left as written, which is enough to compare against a plain
   /// name like `package`.
   pub fn unquoted_text(&self) -> &str {
+    let text = self.text.as_ref();
     for quote in ['"', '\''] {
-      if let Some(inner) = self.text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+      if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
         return inner;
       }
     }
-    self.text
+    text
   }
 }
 
@@ -176,6 +273,9 @@ pub struct LineContext {
 #[derive(Debug, Clone)]
 pub struct Value<'a> {
   pub kind: ValueKind<'a>,
+  /// Where the value was written. Empty for one the formatter made up, which is never written as
+  /// it was.
+  pub span: Span,
 }
 
 impl Value<'_> {
@@ -238,9 +338,9 @@ impl Value<'_> {
 #[derive(Debug, Clone)]
 pub enum ValueKind<'a> {
   /// A single-line value kept verbatim: a string, number, boolean or date-time.
-  Scalar(&'a str),
+  Scalar(Cow<'a, str>),
   /// A multi-line basic or literal string, kept verbatim including its newlines.
-  MultiLineString(&'a str),
+  MultiLineString(Cow<'a, str>),
   Array(Array<'a>),
   InlineTable(InlineTable<'a>),
 }
@@ -297,6 +397,8 @@ pub struct ArrayValue<'a> {
   /// A comment on the same line as the value, before or after its comma.
   pub trailing_comment: Option<Comment<'a>>,
   pub blank_line_before: bool,
+  /// Whether a `# dprint-ignore` directive above the value asks for it to be written as it was.
+  pub ignored: bool,
 }
 
 /// An inline table (`{ a = 1, b = 2 }`).
@@ -347,6 +449,10 @@ impl InlineTable<'_> {
   /// accepts. A table the author wrote that way is kept that way unless it is asked to collapse,
   /// but one holding a comment anywhere within it has no choice.
   pub fn force_use_new_lines(&self, config: &Configuration) -> bool {
+    // TOML 1.0 has no such layout, and a document read as 1.0 has no comment within a table either
+    if config.toml_version == TomlVersion::V1_0 {
+      return false;
+    }
     self.contains_own_comment() || (!config.inline_table_prefer_single_line && self.multi_line_in_source)
   }
 }
@@ -2,10 +2,17 @@ use std::cmp::Ordering;
 use std::path::Path;
 
 use crate::ast::*;
+use crate::configuration::Configuration;
+use crate::configuration::SortOrder;
+use crate::configuration::UnlistedKeys;
+use crate::sorting::compare_in_key_order;
+use crate::sorting::compare_text;
+use crate::sorting::compare_values;
+use crate::sorting::key_order_for;
 use crate::sorting::section_end;
 use crate::sorting::sort_root_entries;
 use crate::sorting::sort_with_comments;
-use crate::sorting::value_sort_key;
+use crate::sorting::table_path;
 
 pub fn is_cargo_toml_file(file_path: &Path) -> bool {
   // don't need to worry about different casing because Cargo.toml will
@@ -41,7 +48,10 @@ fn section_of(header: &TableHeader) -> Section {
   }
 }
 
-pub fn apply_cargo_toml_conventions(root: &mut Root) {
+/// The order of the keys of `[package]`, the rest of which are sorted where the `*` is.
+const PACKAGE_KEY_ORDER: [&str; 4] = ["name", "version", "*", "description"];
+
+pub fn apply_cargo_toml_conventions(root: &mut Root, config: &Configuration) {
   let mut index = 0;
   let mut last_header = Section::Other;
 
@@ -50,9 +60,16 @@ pub fn apply_cargo_toml_conventions(root: &mut Root) {
       RootItem::TableHeader(header) => {
         let section = section_of(header);
         let end = section_end(&root.items, index + 1);
+        // a table given an order with `keyOrder` has already been sorted the way it was asked to be
+        let has_key_order = key_order_for(config, &table_path(&header.key)).is_some();
         match section {
-          Section::Package => sort_root_entries(&mut root.items, index + 1, end, &sort_cargo_package_section),
-          Section::Dependencies => sort_root_entries(&mut root.items, index + 1, end, &|left, right| entry_sort_key(left).cmp(entry_sort_key(right))),
+          _ if has_key_order => {}
+          Section::Package => sort_root_entries(&mut root.items, index + 1, end, &|left, right| {
+            sort_cargo_package_section(left, right, config.sort_order)
+          }),
+          Section::Dependencies => sort_root_entries(&mut root.items, index + 1, end, &|left, right| {
+            compare_text(entry_sort_key(left), entry_sort_key(right), config.sort_order)
+          }),
           Section::Workspace | Section::Other => {}
         }
         last_header = section;
@@ -60,9 +77,9 @@ pub fn apply_cargo_toml_conventions(root: &mut Root) {
         index += 1;
       }
       RootItem::Entry(entry) => {
-        if last_header == Section::Workspace && entry_sort_key(entry) == "members" {
+        if last_header == Section::Workspace && entry_sort_key(entry) == "members" && !entry.ignored {
           if let RootItem::Entry(entry) = &mut root.items[index] {
-            sort_workspace_members(entry);
+            sort_workspace_members(entry, config.sort_order);
           }
         }
         index += 1;
@@ -79,25 +96,12 @@ fn entry_sort_key<'a>(entry: &'a Entry<'_>) -> &'a str {
   entry.key.first.unquoted_text()
 }
 
-fn sort_cargo_package_section(left: &Entry, right: &Entry) -> Ordering {
-  match (entry_sort_key(left), entry_sort_key(right)) {
-    // the ranked arms below would otherwise answer the same way in both directions for two entries
-    // sharing a key, which is not an ordering and leaves `sort_by` free to do anything
-    (left, right) if left == right => Ordering::Equal,
-    ("version", "name") => Ordering::Greater,
-    ("name", _) => Ordering::Less,
-    ("version", _) => Ordering::Less,
-    ("description", _) => Ordering::Greater,
-    (_, "name") => Ordering::Greater,
-    (_, "version") => Ordering::Greater,
-    (_, "description") => Ordering::Less,
-
-    (left, right) => left.cmp(right),
-  }
+fn sort_cargo_package_section(left: &Entry, right: &Entry, order: SortOrder) -> Ordering {
+  compare_in_key_order(&PACKAGE_KEY_ORDER, UnlistedKeys::Sort, order, left, right)
 }
 
 /// Sorts the string members of a `[workspace]` `members` array.
-fn sort_workspace_members(entry: &mut Entry) {
+fn sort_workspace_members(entry: &mut Entry, order: SortOrder) {
   let ValueKind::Array(array) = &mut entry.value.kind else {
     return;
   };
@@ -111,5 +115,5 @@ fn sort_workspace_members(entry: &mut Entry) {
   // Sorted by contents rather than by the text as written: the quote a member happens to be
   // written with is not part of its name, and `quoteStyle` may go on to rewrite it anyway, which
   // would leave the members looking unsorted.
-  sort_with_comments(&mut array.values, |left, right| value_sort_key(&left.value).cmp(&value_sort_key(&right.value)));
+  sort_with_comments(&mut array.values, |left, right| compare_values(&left.value, &right.value, order));
 }
@@ -77,6 +77,13 @@ impl ConfigurationBuilder {
     self.insert("quoteStyle", value.to_string().into())
   }
 
+  /// How to quote each segment of a key, in an entry and in a table header alike.
+  ///
+  /// Default: `KeyQuoteStyle::Maintain`
+  pub fn key_quote_style(&mut self, value: KeyQuoteStyle) -> &mut Self {
+    self.insert("keyQuoteStyle", value.to_string().into())
+  }
+
   /// Whether to indent a table header that is a subtable of the one before it.
   ///
   /// Default: `IndentKind::Maintain`
@@ -119,6 +126,26 @@ impl ConfigurationBuilder {
     self.insert("sortArrays", value.into())
   }
 
+  /// Sorts the tables of the arrays whose dotted path matches `array` by the value each has for
+  /// `key`, keeping those that have no such value at the end in the order they were written. An
+  /// array of tables written as `[[table]]` sections is sorted too, each element carrying the
+  /// sections of the tables within it, where its sections follow one another. In `array`, `*`
+  /// stands for any run of characters; when several patterns match, the one added first wins.
+  ///
+  /// Default: no arrays
+  pub fn sort_arrays_by(&mut self, array: &str, key: &str) -> &mut Self {
+    self.insert_into_object("sortArraysBy", array, key.into())
+  }
+
+  /// Whether to drop the values of an array that repeat an earlier one, moving their comments onto
+  /// it. Strings are compared by their contents and numbers by the number they spell. An inline
+  /// table entry repeating both the key and the value of an earlier one is dropped too.
+  ///
+  /// Default: `false`
+  pub fn dedupe_arrays(&mut self, value: bool) -> &mut Self {
+    self.insert("dedupeArrays", value.into())
+  }
+
   /// Whether to alphabetically sort the entries of an inline table.
   ///
   /// Default: `false`
@@ -126,6 +153,40 @@ impl ConfigurationBuilder {
     self.insert("sortInlineTables", value.into())
   }
 
+  /// Whether to alphabetically sort the sections of the file by their headers. A subtable stays
+  /// beneath its parent table, and the elements of an array of tables keep their order.
+  ///
+  /// Default: `false`
+  pub fn sort_tables(&mut self, value: bool) -> &mut Self {
+    self.insert("sortTables", value.into())
+  }
+
+  /// How the sorting options, and the Cargo.toml conventions, compare the text of keys and values.
+  ///
+  /// Default: `SortOrder::Ordinal`
+  pub fn sort_order(&mut self, value: SortOrder) -> &mut Self {
+    self.insert("sortOrder", value.to_string().into())
+  }
+
+  /// Writes the keys of the tables whose dotted path matches `table` in the order of `keys`. In
+  /// `table`, `*` stands for any run of characters, so `dependencies.*` matches every inline table
+  /// in `[dependencies]`; when several patterns match, the one added first wins. A `*` in `keys`
+  /// marks where the keys not listed go, which is otherwise after all of those that are.
+  ///
+  /// Default: no tables
+  pub fn key_order(&mut self, table: &str, keys: &[&str]) -> &mut Self {
+    let keys = ConfigKeyValue::Array(keys.iter().map(|&key| key.into()).collect());
+    self.insert_into_object("keyOrder", table, keys)
+  }
+
+  /// Whether the keys a table's `keyOrder` list doesn't name are sorted alphabetically or kept in
+  /// the order they were written.
+  ///
+  /// Default: `UnlistedKeys::Sort`
+  pub fn key_order_unlisted(&mut self, value: UnlistedKeys) -> &mut Self {
+    self.insert("keyOrder.unlisted", value.to_string().into())
+  }
+
   /// Whether to collapse an array or inline table onto a single line when it fits, even when it
   /// was written over several lines.
   ///
@@ -164,6 +225,53 @@ impl ConfigurationBuilder {
     self.insert("inlineTable.spaceSurroundingBraces", value.into())
   }
 
+  /// Whether to turn an entry whose value is an inline table too long for the line width into a
+  /// `[table]` section, which is written after the rest of the section the entry was in.
+  ///
+  /// Default: `false`
+  pub fn inline_table_overflow_to_table(&mut self, value: bool) -> &mut Self {
+    self.insert("inlineTable.overflowToTable", value.into())
+  }
+
+  /// Whether to turn the section of a small subtable into an inline table within the section of
+  /// the nearest table above it that it belongs to. Only a section with no comments is collapsed,
+  /// and only when the inline table fits on a single line.
+  ///
+  /// Default: `false`
+  pub fn inline_table_collapse_tables(&mut self, value: bool) -> &mut Self {
+    self.insert("inlineTable.collapseTables", value.into())
+  }
+
+  /// The most entries a subtable's section may have for `inlineTable.collapseTables` to collapse it.
+  ///
+  /// Default: `3`
+  pub fn inline_table_collapse_max_entries(&mut self, value: u32) -> &mut Self {
+    self.insert("inlineTable.collapseMaxEntries", (value as i32).into())
+  }
+
+  /// Whether to write an array of tables as `[[table]]` sections or as an array of inline tables.
+  /// The sections are only made inline when each element fits on a line and holds no comments
+  /// within it, and when no other header names a table within them.
+  ///
+  /// Default: `ArrayOfTablesStyle::Maintain`
+  pub fn array_of_tables(&mut self, value: ArrayOfTablesStyle) -> &mut Self {
+    self.insert("arrayOfTables", value.to_string().into())
+  }
+
+  /// Whether to write a space inside the brackets of a table header.
+  ///
+  /// Default: `false`
+  pub fn table_header_space_surrounding_brackets(&mut self, value: bool) -> &mut Self {
+    self.insert("tableHeader.spaceSurroundingBrackets", value.into())
+  }
+
+  /// Whether to write a space on either side of the dots in a dotted key.
+  ///
+  /// Default: `false`
+  pub fn key_space_surrounding_dots(&mut self, value: bool) -> &mut Self {
+    self.insert("key.spaceSurroundingDots", value.into())
+  }
+
   /// Forces a leading space after the hashes.
   /// Default: `true`
   pub fn comment_force_leading_space(&mut self, value: bool) -> &mut Self {
@@ -176,6 +284,125 @@ impl ConfigurationBuilder {
     self.insert("cargo.applyConventions", value.into())
   }
 
+  /// Whether to reject a file that defines a key or table more than once, which the parser alone
+  /// lets through.
+  ///
+  /// Default: `false`
+  pub fn validate_semantics(&mut self, value: bool) -> &mut Self {
+    self.insert("validateSemantics", value.into())
+  }
+
+  /// The version of TOML to read and write. Targeting 1.0 rejects the syntax TOML 1.1 added, and
+  /// never writes an inline table over several lines.
+  ///
+  /// Default: `TomlVersion::V1_1`
+  pub fn toml_version(&mut self, value: TomlVersion) -> &mut Self {
+    self.insert("tomlVersion", value.to_string().into())
+  }
+
+  /// Whether a file targeting TOML 1.0 may use the syntax TOML 1.1 added, which is then rewritten
+  /// the way TOML 1.0 spells it rather than rejected. Setting it for any other version is reported
+  /// as a diagnostic.
+  ///
+  /// Default: `false`
+  pub fn downlevel(&mut self, value: bool) -> &mut Self {
+    self.insert("downlevel", value.into())
+  }
+
+  /// The case of the digits in a hexadecimal number. The `0x` prefix is always lowercase.
+  ///
+  /// Default: `LetterCase::Maintain`
+  pub fn numbers_hex_digit_case(&mut self, value: LetterCase) -> &mut Self {
+    self.insert("numbers.hexDigitCase", value.to_string().into())
+  }
+
+  /// The case of the `e` that begins a float's exponent.
+  ///
+  /// Default: `LetterCase::Maintain`
+  pub fn numbers_exponent_case(&mut self, value: LetterCase) -> &mut Self {
+    self.insert("numbers.exponentCase", value.to_string().into())
+  }
+
+  /// Whether to remove a `+` sign, which says nothing a number without one doesn't, from a number
+  /// and from a float's exponent.
+  ///
+  /// Default: `false`
+  pub fn numbers_remove_plus_sign(&mut self, value: bool) -> &mut Self {
+    self.insert("numbers.removePlusSign", value.into())
+  }
+
+  /// Whether to separate the digits of a long integer written without any separators with `_`,
+  /// every three digits for a decimal integer and every four for a hexadecimal or binary one.
+  ///
+  /// Default: `false`
+  pub fn numbers_digit_separators(&mut self, value: bool) -> &mut Self {
+    self.insert("numbers.digitSeparators", value.into())
+  }
+
+  /// What separates the date from the time in a date-time.
+  ///
+  /// Default: `DateTimeSeparator::Maintain`
+  pub fn date_time_separator(&mut self, value: DateTimeSeparator) -> &mut Self {
+    self.insert("dateTime.separator", value.to_string().into())
+  }
+
+  /// The case of the `Z` that stands for a UTC offset.
+  ///
+  /// Default: `LetterCase::Maintain`
+  pub fn date_time_offset_case(&mut self, value: LetterCase) -> &mut Self {
+    self.insert("dateTime.offsetCase", value.to_string().into())
+  }
+
+  /// Whether to remove the zeros trailing the fractional seconds of a time, along with the decimal
+  /// point when nothing else is left.
+  ///
+  /// Default: `false`
+  pub fn date_time_trim_fractional_seconds(&mut self, value: bool) -> &mut Self {
+    self.insert("dateTime.trimFractionalSeconds", value.into())
+  }
+
+  /// The fewest digits of fractional seconds to write in a time that has seconds, padding it with
+  /// zeros to reach them. Zero leaves the fractional seconds as long as they are.
+  ///
+  /// Default: `0`
+  pub fn date_time_pad_fractional_seconds(&mut self, value: u8) -> &mut Self {
+    self.insert("dateTime.padFractionalSeconds", (value as i32).into())
+  }
+
+  /// Whether to rewrite the escapes in a single-line basic string the one way each character is
+  /// best written: as itself when it is printable, with a short escape like `\n` where it has one,
+  /// and otherwise with a `\u` escape. The string's value never changes.
+  ///
+  /// Default: `false`
+  pub fn escapes_normalize(&mut self, value: bool) -> &mut Self {
+    self.insert("escapes.normalize", value.into())
+  }
+
+  /// The case of the hexadecimal digits in the escapes written by `escapes.normalize`. Maintaining
+  /// it keeps the case an escape was written in, and uses uppercase in one that wasn't an escape.
+  ///
+  /// Default: `LetterCase::Maintain`
+  pub fn escapes_hex_digit_case(&mut self, value: LetterCase) -> &mut Self {
+    self.insert("escapes.hexDigitCase", value.to_string().into())
+  }
+
+  /// Whether to write a table with dotted keys or as a `[table]` section of its own. Expanding
+  /// moves the entries with dotted keys into sections named by their keys, and collapsing writes
+  /// the section of a small subtable as dotted keys within the section of the nearest table above
+  /// it that it belongs to.
+  ///
+  /// Default: `DottedKeys::Maintain`
+  pub fn dotted_keys(&mut self, value: DottedKeys) -> &mut Self {
+    self.insert("dottedKeys", value.to_string().into())
+  }
+
+  /// The most entries a subtable's section may have for `dottedKeys: collapse` to collapse it.
+  ///
+  /// Default: `3`
+  pub fn dotted_keys_collapse_max_entries(&mut self, value: u32) -> &mut Self {
+    self.insert("dottedKeys.collapseMaxEntries", (value as i32).into())
+  }
+
   #[cfg(test)]
   pub(super) fn get_inner_config(&self) -> ConfigKeyMap {
     self.config.clone()
@@ -185,6 +412,17 @@ impl ConfigurationBuilder {
     self.config.insert(String::from(name), value);
     self
   }
+
+  /// Adds `key` to the object option `name`, creating the option if it isn't set yet.
+  fn insert_into_object(&mut self, name: &str, key: &str, value: ConfigKeyValue) -> &mut Self {
+    match self.config.get_mut(name) {
+      Some(ConfigKeyValue::Object(object)) => {
+        object.insert(key.to_string(), value);
+        self
+      }
+      _ => self.insert(name, ConfigKeyValue::Object(ConfigKeyMap::from([(key.to_string(), value)]))),
+    }
+  }
 }
 
 #[cfg(test)]
@@ -204,27 +442,65 @@ mod tests {
       .indent_width(4)
       .new_line_kind(NewLineKind::CarriageReturnLineFeed)
       .quote_style(QuoteStyle::Maintain)
+      .key_quote_style(KeyQuoteStyle::BareOrDouble)
       .indent_tables(IndentKind::Always)
       .indent_entries(IndentKind::Always)
       .trailing_commas(TrailingCommaKind::Never)
       .space_surrounding_equals(false)
       .sort_keys(true)
       .sort_arrays(true)
+      .sort_arrays_by("package.authors", "name")
+      .dedupe_arrays(true)
       .sort_inline_tables(true)
+      .sort_tables(true)
+      .sort_order(SortOrder::Natural)
+      .key_order("tool.poetry", &["name", "version", "*", "description"])
+      .key_order("*", &["name"])
+      .key_order_unlisted(UnlistedKeys::Maintain)
       .prefer_single_line(true)
       .array_prefer_single_line(true)
       .array_space_surrounding_brackets(true)
       .inline_table_prefer_single_line(true)
       .inline_table_space_surrounding_braces(false)
+      .inline_table_overflow_to_table(true)
+      .inline_table_collapse_tables(true)
+      .inline_table_collapse_max_entries(2)
+      .array_of_tables(ArrayOfTablesStyle::Inline)
+      .table_header_space_surrounding_brackets(true)
+      .key_space_surrounding_dots(true)
       .comment_force_leading_space(false)
-      .cargo_apply_conventions(false);
+      .cargo_apply_conventions(false)
+      .validate_semantics(true)
+      .toml_version(TomlVersion::V1_0)
+      .downlevel(true)
+      .numbers_hex_digit_case(LetterCase::Upper)
+      .numbers_exponent_case(LetterCase::Lower)
+      .numbers_remove_plus_sign(true)
+      .numbers_digit_separators(true)
+      .date_time_separator(DateTimeSeparator::Space)
+      .date_time_offset_case(LetterCase::Upper)
+      .date_time_trim_fractional_seconds(true)
+      .date_time_pad_fractional_seconds(3)
+      .escapes_normalize(true)
+      .escapes_hex_digit_case(LetterCase::Lower)
+      .dotted_keys(DottedKeys::Collapse)
+      .dotted_keys_collapse_max_entries(5);
 
     let inner_config = config.get_inner_config();
-    assert_eq!(inner_config.len(), 19);
+    assert_eq!(inner_config.len(), 47);
     let diagnostics = resolve_config(inner_config, &Default::default()).diagnostics;
     assert_eq!(diagnostics.len(), 0);
   }
 
+  #[test]
+  fn report_downlevel_without_toml_1_0() {
+    let mut config = ConfigurationBuilder::new();
+    config.downlevel(true);
+    let diagnostics = resolve_config(config.get_inner_config(), &Default::default()).diagnostics;
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].property_name, "downlevel");
+  }
+
   #[test]
   fn handle_global_config() {
     let mut global_config = ConfigKeyMap::new();
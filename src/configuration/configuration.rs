@@ -16,13 +16,44 @@ pub enum QuoteStyle {
   /// Uses a single quote when doing so doesn't require adding an escape.
   #[serde(rename = "preferSingle")]
   PreferSingle,
+  /// Always uses a double quote, escaping whatever the string holds that needs it.
+  #[serde(rename = "alwaysDouble")]
+  AlwaysDouble,
+  /// Uses a single quote for any string a literal string can hold, which is one without a single
+  /// quote, a newline or another control character in it.
+  #[serde(rename = "alwaysSingle")]
+  AlwaysSingle,
 }
 
 generate_str_to_from![
   QuoteStyle,
   [Maintain, "maintain"],
   [PreferDouble, "preferDouble"],
-  [PreferSingle, "preferSingle"]
+  [PreferSingle, "preferSingle"],
+  [AlwaysDouble, "alwaysDouble"],
+  [AlwaysSingle, "alwaysSingle"]
+];
+
+/// How a key segment is quoted.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum KeyQuoteStyle {
+  /// Keeps the segment as it was written.
+  #[serde(rename = "maintain")]
+  Maintain,
+  /// Writes the segment bare when it can be, and with a double quote when it can't.
+  #[serde(rename = "bareOrDouble")]
+  BareOrDouble,
+  /// Writes the segment bare when it can be, and with a single quote when it can't, unless it
+  /// holds something only a basic string can.
+  #[serde(rename = "bareOrSingle")]
+  BareOrSingle,
+}
+
+generate_str_to_from![
+  KeyQuoteStyle,
+  [Maintain, "maintain"],
+  [BareOrDouble, "bareOrDouble"],
+  [BareOrSingle, "bareOrSingle"]
 ];
 
 /// Whether something is indented.
@@ -54,6 +85,132 @@ pub enum TrailingCommaKind {
 
 generate_str_to_from![TrailingCommaKind, [OnlyMultiLine, "onlyMultiLine"], [Never, "never"]];
 
+/// The version of TOML a file is read and written as.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum TomlVersion {
+  /// TOML 1.0, which has no multi-line inline tables, `\e` or `\xHH` escapes, or times without
+  /// seconds.
+  #[serde(rename = "1.0")]
+  V1_0,
+  /// TOML 1.1.
+  #[serde(rename = "1.1")]
+  V1_1,
+}
+
+generate_str_to_from![TomlVersion, [V1_0, "1.0"], [V1_1, "1.1"]];
+
+/// Which case the letters of something are written in.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum LetterCase {
+  /// Keeps the case they were written in.
+  #[serde(rename = "maintain")]
+  Maintain,
+  /// Writes them in lowercase.
+  #[serde(rename = "lower")]
+  Lower,
+  /// Writes them in uppercase.
+  #[serde(rename = "upper")]
+  Upper,
+}
+
+generate_str_to_from![LetterCase, [Maintain, "maintain"], [Lower, "lower"], [Upper, "upper"]];
+
+/// What separates the date from the time in a date-time.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum DateTimeSeparator {
+  /// Keeps the separator it was written with.
+  #[serde(rename = "maintain")]
+  Maintain,
+  /// Separates them with a `T`.
+  #[serde(rename = "T")]
+  T,
+  /// Separates them with a space.
+  #[serde(rename = "space")]
+  Space,
+}
+
+generate_str_to_from![DateTimeSeparator, [Maintain, "maintain"], [T, "T"], [Space, "space"]];
+
+/// Whether a table is written with dotted keys or as a `[table]` section of its own.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum DottedKeys {
+  /// Keeps each table written the way it was.
+  #[serde(rename = "maintain")]
+  Maintain,
+  /// Moves the entries with dotted keys into sections named by their keys.
+  #[serde(rename = "expand")]
+  Expand,
+  /// Writes the section of a small subtable as dotted keys within the section above it.
+  #[serde(rename = "collapse")]
+  Collapse,
+}
+
+generate_str_to_from![DottedKeys, [Maintain, "maintain"], [Expand, "expand"], [Collapse, "collapse"]];
+
+/// Whether an array of tables is written as `[[table]]` sections or as an array of inline tables.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum ArrayOfTablesStyle {
+  /// Keeps each array of tables written the way it was.
+  #[serde(rename = "maintain")]
+  Maintain,
+  /// Writes the `[[table]]` sections as an array of inline tables, when each fits on a line.
+  #[serde(rename = "inline")]
+  Inline,
+  /// Writes an array of inline tables as `[[table]]` sections.
+  #[serde(rename = "sections")]
+  Sections,
+}
+
+generate_str_to_from![ArrayOfTablesStyle, [Maintain, "maintain"], [Inline, "inline"], [Sections, "sections"]];
+
+/// How the sorting options compare the text of keys and values.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum SortOrder {
+  /// Compares character by character, so that `Zlib` comes before `apache` and `item10` before
+  /// `item2`.
+  #[serde(rename = "ordinal")]
+  Ordinal,
+  /// Compares without regard to case.
+  #[serde(rename = "caseInsensitive")]
+  CaseInsensitive,
+  /// Compares without regard to case, and compares each run of digits by the number it spells.
+  #[serde(rename = "natural")]
+  Natural,
+}
+
+generate_str_to_from![SortOrder, [Ordinal, "ordinal"], [CaseInsensitive, "caseInsensitive"], [Natural, "natural"]];
+
+/// Where `keyOrder` puts the keys a table's list doesn't name.
+#[derive(Clone, PartialEq, Eq, Debug, Copy, Serialize, Deserialize)]
+pub enum UnlistedKeys {
+  /// Sorts them alphabetically among themselves.
+  #[serde(rename = "sort")]
+  Sort,
+  /// Keeps them in the order they were written.
+  #[serde(rename = "maintain")]
+  Maintain,
+}
+
+generate_str_to_from![UnlistedKeys, [Sort, "sort"], [Maintain, "maintain"]];
+
+/// The order `keyOrder` gives the keys of the tables whose dotted path matches `table`.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct KeyOrderRule {
+  /// A table path like `tool.poetry`, where `*` stands for any run of characters.
+  pub table: String,
+  /// The keys in the order they are written, where `*` marks the place of any key not listed.
+  pub keys: Vec<String>,
+}
+
+/// The key `sortArraysBy` sorts the tables of the arrays whose dotted path matches `array` by,
+/// whether they are inline tables or `[[table]]` sections.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct SortArraysByRule {
+  /// An array path like `package.authors`, where `*` stands for any run of characters.
+  pub array: String,
+  pub key: String,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Configuration {
@@ -62,17 +219,45 @@ pub struct Configuration {
   pub indent_width: u8,
   pub new_line_kind: NewLineKind,
   pub quote_style: QuoteStyle,
+  pub key_quote_style: KeyQuoteStyle,
   pub indent_tables: IndentKind,
   pub indent_entries: IndentKind,
   pub trailing_commas: TrailingCommaKind,
   pub space_surrounding_equals: bool,
   pub sort_keys: bool,
   pub sort_arrays: bool,
+  pub sort_arrays_by: Vec<SortArraysByRule>,
+  pub dedupe_arrays: bool,
   pub sort_inline_tables: bool,
+  pub sort_tables: bool,
+  pub sort_order: SortOrder,
+  pub key_order: Vec<KeyOrderRule>,
+  pub key_order_unlisted: UnlistedKeys,
   pub array_prefer_single_line: bool,
   pub array_space_surrounding_brackets: bool,
   pub inline_table_prefer_single_line: bool,
   pub inline_table_space_surrounding_braces: bool,
+  pub inline_table_overflow_to_table: bool,
+  pub inline_table_collapse_tables: bool,
+  pub inline_table_collapse_max_entries: u32,
+  pub array_of_tables: ArrayOfTablesStyle,
+  pub table_header_space_surrounding_brackets: bool,
+  pub key_space_surrounding_dots: bool,
   pub comment_force_leading_space: bool,
   pub cargo_apply_conventions: bool,
+  pub validate_semantics: bool,
+  pub toml_version: TomlVersion,
+  pub downlevel: bool,
+  pub numbers_hex_digit_case: LetterCase,
+  pub numbers_exponent_case: LetterCase,
+  pub numbers_remove_plus_sign: bool,
+  pub numbers_digit_separators: bool,
+  pub date_time_separator: DateTimeSeparator,
+  pub date_time_offset_case: LetterCase,
+  pub date_time_trim_fractional_seconds: bool,
+  pub date_time_pad_fractional_seconds: u8,
+  pub escapes_normalize: bool,
+  pub escapes_hex_digit_case: LetterCase,
+  pub dotted_keys: DottedKeys,
+  pub dotted_keys_collapse_max_entries: u32,
 }
@@ -1,7 +1,17 @@
+use super::ArrayOfTablesStyle;
 use super::Configuration;
+use super::DateTimeSeparator;
+use super::DottedKeys;
 use super::IndentKind;
+use super::KeyOrderRule;
+use super::KeyQuoteStyle;
+use super::LetterCase;
 use super::QuoteStyle;
+use super::SortArraysByRule;
+use super::SortOrder;
+use super::TomlVersion;
 use super::TrailingCommaKind;
+use super::UnlistedKeys;
 use dprint_core::configuration::*;
 
 /// Resolves configuration from a collection of key value strings.
@@ -55,21 +65,57 @@ pub fn resolve_config(config: ConfigKeyMap, global_config: &GlobalConfiguration)
       &mut diagnostics,
     ),
     quote_style: get_value(&mut config, "quoteStyle", QuoteStyle::PreferDouble, &mut diagnostics),
+    key_quote_style: get_value(&mut config, "keyQuoteStyle", KeyQuoteStyle::Maintain, &mut diagnostics),
     indent_tables: get_value(&mut config, "indentTables", IndentKind::Maintain, &mut diagnostics),
     indent_entries: get_value(&mut config, "indentEntries", IndentKind::Maintain, &mut diagnostics),
     trailing_commas: get_value(&mut config, "trailingCommas", TrailingCommaKind::OnlyMultiLine, &mut diagnostics),
     space_surrounding_equals: get_value(&mut config, "spaceSurroundingEquals", true, &mut diagnostics),
     sort_keys: get_value(&mut config, "sortKeys", false, &mut diagnostics),
     sort_arrays: get_value(&mut config, "sortArrays", false, &mut diagnostics),
+    sort_arrays_by: get_sort_arrays_by(&mut config, &mut diagnostics),
+    dedupe_arrays: get_value(&mut config, "dedupeArrays", false, &mut diagnostics),
     sort_inline_tables: get_value(&mut config, "sortInlineTables", false, &mut diagnostics),
+    sort_tables: get_value(&mut config, "sortTables", false, &mut diagnostics),
+    sort_order: get_value(&mut config, "sortOrder", SortOrder::Ordinal, &mut diagnostics),
+    key_order: get_key_order(&mut config, &mut diagnostics),
+    key_order_unlisted: get_value(&mut config, "keyOrder.unlisted", UnlistedKeys::Sort, &mut diagnostics),
     array_prefer_single_line: get_value(&mut config, "array.preferSingleLine", prefer_single_line, &mut diagnostics),
     array_space_surrounding_brackets: get_value(&mut config, "array.spaceSurroundingBrackets", false, &mut diagnostics),
     inline_table_prefer_single_line: get_value(&mut config, "inlineTable.preferSingleLine", prefer_single_line, &mut diagnostics),
     inline_table_space_surrounding_braces: get_value(&mut config, "inlineTable.spaceSurroundingBraces", true, &mut diagnostics),
+    inline_table_overflow_to_table: get_value(&mut config, "inlineTable.overflowToTable", false, &mut diagnostics),
+    inline_table_collapse_tables: get_value(&mut config, "inlineTable.collapseTables", false, &mut diagnostics),
+    inline_table_collapse_max_entries: get_value(&mut config, "inlineTable.collapseMaxEntries", 3, &mut diagnostics),
+    array_of_tables: get_value(&mut config, "arrayOfTables", ArrayOfTablesStyle::Maintain, &mut diagnostics),
+    table_header_space_surrounding_brackets: get_value(&mut config, "tableHeader.spaceSurroundingBrackets", false, &mut diagnostics),
+    key_space_surrounding_dots: get_value(&mut config, "key.spaceSurroundingDots", false, &mut diagnostics),
     comment_force_leading_space: get_value(&mut config, "comment.forceLeadingSpace", true, &mut diagnostics),
     cargo_apply_conventions: get_value(&mut config, "cargo.applyConventions", true, &mut diagnostics),
+    validate_semantics: get_value(&mut config, "validateSemantics", false, &mut diagnostics),
+    toml_version: get_value(&mut config, "tomlVersion", TomlVersion::V1_1, &mut diagnostics),
+    downlevel: get_value(&mut config, "downlevel", false, &mut diagnostics),
+    numbers_hex_digit_case: get_value(&mut config, "numbers.hexDigitCase", LetterCase::Maintain, &mut diagnostics),
+    numbers_exponent_case: get_value(&mut config, "numbers.exponentCase", LetterCase::Maintain, &mut diagnostics),
+    numbers_remove_plus_sign: get_value(&mut config, "numbers.removePlusSign", false, &mut diagnostics),
+    numbers_digit_separators: get_value(&mut config, "numbers.digitSeparators", false, &mut diagnostics),
+    date_time_separator: get_value(&mut config, "dateTime.separator", DateTimeSeparator::Maintain, &mut diagnostics),
+    date_time_offset_case: get_value(&mut config, "dateTime.offsetCase", LetterCase::Maintain, &mut diagnostics),
+    date_time_trim_fractional_seconds: get_value(&mut config, "dateTime.trimFractionalSeconds", false, &mut diagnostics),
+    date_time_pad_fractional_seconds: get_value(&mut config, "dateTime.padFractionalSeconds", 0, &mut diagnostics),
+    escapes_normalize: get_value(&mut config, "escapes.normalize", false, &mut diagnostics),
+    escapes_hex_digit_case: get_value(&mut config, "escapes.hexDigitCase", LetterCase::Maintain, &mut diagnostics),
+    dotted_keys: get_value(&mut config, "dottedKeys", DottedKeys::Maintain, &mut diagnostics),
+    dotted_keys_collapse_max_entries: get_value(&mut config, "dottedKeys.collapseMaxEntries", 3, &mut diagnostics),
   };
 
+  // TOML 1.1 accepts everything downleveling rewrites, so there's nothing for it to do
+  if resolved_config.downlevel && resolved_config.toml_version != TomlVersion::V1_0 {
+    diagnostics.push(ConfigurationDiagnostic {
+      property_name: "downlevel".to_string(),
+      message: "Has no effect unless tomlVersion is 1.0.".to_string(),
+    });
+  }
+
   diagnostics.extend(get_unknown_property_diagnostics(config));
 
   ResolveConfigurationResult {
@@ -77,3 +123,68 @@ pub fn resolve_config(config: ConfigKeyMap, global_config: &GlobalConfiguration)
     diagnostics,
   }
 }
+
+/// Reads `keyOrder`, an object mapping each table path pattern to the list of keys in the order
+/// they're written, keeping the patterns in the order they were given so the first to match wins.
+fn get_key_order(config: &mut ConfigKeyMap, diagnostics: &mut Vec<ConfigurationDiagnostic>) -> Vec<KeyOrderRule> {
+  let keys = |value| match value {
+    ConfigKeyValue::Array(keys) => keys
+      .into_iter()
+      .map(|key| match key {
+        ConfigKeyValue::String(key) => Some(key),
+        _ => None,
+      })
+      .collect(),
+    _ => None,
+  };
+  get_path_patterns(config, "keyOrder", "an array of keys", keys, diagnostics)
+    .into_iter()
+    .map(|(table, keys)| KeyOrderRule { table, keys })
+    .collect()
+}
+
+/// Reads `sortArraysBy`, an object mapping each array path pattern to the key its inline tables
+/// are sorted by.
+fn get_sort_arrays_by(config: &mut ConfigKeyMap, diagnostics: &mut Vec<ConfigurationDiagnostic>) -> Vec<SortArraysByRule> {
+  let key = |value| match value {
+    ConfigKeyValue::String(key) => Some(key),
+    _ => None,
+  };
+  get_path_patterns(config, "sortArraysBy", "a key", key, diagnostics)
+    .into_iter()
+    .map(|(array, key)| SortArraysByRule { array, key })
+    .collect()
+}
+
+/// Reads an object mapping path patterns to values `parse` reads, keeping the patterns in the
+/// order they were given so the first to match wins. `expected` says what `parse` reads, for the
+/// diagnostic about a value it can't.
+fn get_path_patterns<T>(
+  config: &mut ConfigKeyMap,
+  key: &str,
+  expected: &str,
+  parse: impl Fn(ConfigKeyValue) -> Option<T>,
+  diagnostics: &mut Vec<ConfigurationDiagnostic>,
+) -> Vec<(String, T)> {
+  let mut patterns = Vec::new();
+  let Some(value) = config.shift_remove(key) else {
+    return patterns;
+  };
+  let ConfigKeyValue::Object(object) = value else {
+    diagnostics.push(ConfigurationDiagnostic {
+      property_name: key.to_string(),
+      message: format!("Expected an object mapping paths to {expected}."),
+    });
+    return patterns;
+  };
+  for (path, value) in object {
+    match parse(value) {
+      Some(value) => patterns.push((path, value)),
+      None => diagnostics.push(ConfigurationDiagnostic {
+        property_name: format!("{key}.{path}"),
+        message: format!("Expected {expected}."),
+      }),
+    }
+  }
+  patterns
+}
@@ -0,0 +1,257 @@
+// Moving entries between dotted keys and `[table]` sections, for the `dottedKeys` option.
+//
+// `a.b.x = 1` under `[t]` defines the same thing as `x = 1` under `[t.a.b]`, but TOML only lets
+// each form go so far. The tables a section defines with dotted keys can't be named by a header
+// anywhere else, and the entries of a section belong to whichever header last came before them.
+// So an expanded table is written as a section of its own straight after the one its entries came
+// from, ahead of any other header. And a section is only collapsed into the nearest header above it
+// naming one of its ancestors -- the element of an array of tables it belongs to, if that is what
+// it is -- and only when no header names a table in between, which its dotted keys would then
+// define instead.
+
+use crate::ast::*;
+use crate::configuration::Configuration;
+use crate::configuration::DottedKeys;
+use crate::parser::Span;
+use crate::sorting::attached_comments_start;
+use crate::sorting::section_content_end;
+use crate::tables;
+
+/// Expands or collapses the dotted keys in `root`, as the `dottedKeys` option asks.
+pub fn apply_dotted_keys(root: &mut Root, config: &Configuration) {
+  match config.dotted_keys {
+    DottedKeys::Maintain => {}
+    DottedKeys::Expand => expand(root),
+    DottedKeys::Collapse => collapse(root, config.dotted_keys_collapse_max_entries),
+  }
+}
+
+/// An entry moving elsewhere, along with the comments written directly above it.
+struct Moved<'a> {
+  comments: Vec<Comment<'a>>,
+  entry: Entry<'a>,
+}
+
+// ---- expand ----
+
+/// Moves every entry with a dotted key into a section named by its key, so that no dotted keys
+/// are left outside of inline tables.
+fn expand(root: &mut Root) {
+  let mut start = 0;
+  // the key and indent of the header the section starting at `start` is under
+  let mut header: Option<(Key, usize)> = None;
+  loop {
+    let end = section_content_end(&root.items, start);
+    let content = root.items.drain(start..end).collect::<Vec<_>>();
+    let (kept, sections) = expand_section(content, header.as_ref());
+    let expanded_end = start + kept.len() + sections.len();
+    root.items.splice(start..start, kept.into_iter().chain(sections));
+
+    let Some(offset) = root.items[expanded_end..].iter().position(RootItem::is_table_header) else {
+      break;
+    };
+    let RootItem::TableHeader(next) = &root.items[expanded_end + offset] else {
+      unreachable!();
+    };
+    header = Some((next.key.clone(), next.indent_in_source));
+    start = expanded_end + offset + 1;
+  }
+}
+
+/// Splits a section's content into what stays and the sections its dotted keys become.
+fn expand_section<'a>(content: Vec<RootItem<'a>>, header: Option<&(Key<'a>, usize)>) -> (Vec<RootItem<'a>>, Vec<RootItem<'a>>) {
+  let mut kept = Vec::with_capacity(content.len());
+  let mut moved = Vec::new();
+  for item in content {
+    let is_dotted = matches!(&item, RootItem::Entry(entry) if !entry.key.rest.is_empty() && !entry.ignored);
+    kept.push(item);
+    if is_dotted {
+      let unit_start = attached_comments_start(&kept, kept.len() - 1);
+      moved.push(into_moved(kept.drain(unit_start..)));
+    }
+  }
+
+  let mut sections = Vec::new();
+  let indent = header.map(|(_, indent)| *indent).unwrap_or(0);
+  for (part, group) in group_by_first_part(moved) {
+    write_section(child_key(header.map(|(key, _)| key), part), indent, group, &mut sections);
+  }
+  (kept, sections)
+}
+
+/// Writes a section named `key` holding the entries whose keys now have a single segment left,
+/// followed by the sections for those whose keys are still dotted. A table with no entries of its
+/// own gets no header, since the sections beneath it already define it.
+fn write_section<'a>(key: Key<'a>, indent: usize, units: Vec<Moved<'a>>, sections: &mut Vec<RootItem<'a>>) {
+  let (direct, nested): (Vec<_>, Vec<_>) = units.into_iter().partition(|unit| unit.entry.key.rest.is_empty());
+  if !direct.is_empty() {
+    sections.push(RootItem::TableHeader(TableHeader {
+      key: key.clone(),
+      is_array_of_tables: false,
+      blank_line_before: true,
+      trailing_comment: None,
+      indent_in_source: indent,
+      span: Span::default(),
+      ignored: false,
+    }));
+    for (i, unit) in direct.into_iter().enumerate() {
+      push_moved(unit, i == 0, sections);
+    }
+  }
+  for (part, group) in group_by_first_part(nested) {
+    write_section(child_key(Some(&key), part), indent, group, sections);
+  }
+}
+
+/// Groups entries by the first segment of their key, which is taken off, keeping the groups in the
+/// order they first appear.
+fn group_by_first_part(units: Vec<Moved<'_>>) -> Vec<(KeyPart<'_>, Vec<Moved<'_>>)> {
+  let mut groups: Vec<(KeyPart, Vec<Moved>)> = Vec::new();
+  for mut unit in units {
+    let rest = unit.entry.key.rest.remove(0);
+    let first = std::mem::replace(&mut unit.entry.key.first, rest);
+    match groups.iter_mut().find(|(part, _)| part.unquoted_text() == first.unquoted_text()) {
+      Some((_, group)) => group.push(unit),
+      None => groups.push((first, vec![unit])),
+    }
+  }
+  groups
+}
+
+fn child_key<'a>(parent: Option<&Key<'a>>, part: KeyPart<'a>) -> Key<'a> {
+  match parent {
+    Some(parent) => Key {
+      first: parent.first.clone(),
+      rest: parent.rest.iter().cloned().chain(std::iter::once(part)).collect(),
+    },
+    None => Key { first: part, rest: Vec::new() },
+  }
+}
+
+// ---- collapse ----
+
+/// Turns each subtable's section with no more than `max_entries` entries into dotted keys within
+/// the section of its nearest ancestor, working from the end of the file so that a collapsed section
+/// can go on to collapse into its own ancestor in turn.
+fn collapse(root: &mut Root, max_entries: u32) {
+  let mut search_end = root.items.len();
+  while let Some(index) = root.items[..search_end].iter().rposition(RootItem::is_table_header) {
+    search_end = index;
+    if let Some(target) = collapse_target(&root.items, index, max_entries) {
+      search_end = collapse_section(&mut root.items, index, target);
+    }
+  }
+}
+
+/// The index of the header the section whose header is at `index` can be collapsed into, or `None`
+/// when it can't be collapsed.
+fn collapse_target(items: &[RootItem], index: usize, max_entries: u32) -> Option<usize> {
+  let RootItem::TableHeader(header) = &items[index] else {
+    unreachable!();
+  };
+  if header.is_array_of_tables {
+    return None;
+  }
+  let end = section_content_end(items, index + 1);
+  let entries = items[index + 1..end].iter().filter(|item| matches!(item, RootItem::Entry(_))).count();
+  // an empty section defines its table, which no entry would be left to do
+  if entries == 0 || entries > max_entries as usize {
+    return None;
+  }
+
+  let target = tables::enclosing_section(items, index)?;
+  tables::can_move(items, attached_comments_start(items, index)..end, section_content_end(items, target + 1)).then_some(target)
+}
+
+/// Moves the section whose header is at `index` to the end of the section of the header at
+/// `target`, giving its entries the segments of its key that the target's lacks. Returns the index
+/// the items that came before the collapsed section now end at.
+fn collapse_section(items: &mut Vec<RootItem>, index: usize, target: usize) -> usize {
+  let insert_at = section_content_end(items, target + 1);
+  let target_len = tables::header_len(&items[target]);
+
+  let removed_start = attached_comments_start(items, index);
+  let removed_end = section_content_end(items, index + 1);
+  let mut removed = items.drain(removed_start..removed_end).collect::<Vec<_>>();
+  let header_position = index - removed_start;
+  let RootItem::TableHeader(header) = removed.remove(header_position) else {
+    unreachable!();
+  };
+  let prefix = header.key.parts().skip(target_len).cloned().collect::<Vec<_>>();
+  let body_indent = removed[header_position..]
+    .iter()
+    .find_map(|item| match item {
+      RootItem::Entry(entry) => Some(entry.indent_in_source),
+      _ => None,
+    })
+    .unwrap_or(header.indent_in_source);
+
+  // the comment beside the header moves above its entries, along with those above it
+  let mut trailing_comment = header.trailing_comment;
+  if let Some(comment) = &mut trailing_comment {
+    comment.indent_in_source = body_indent;
+  }
+  let mut moved = Vec::with_capacity(removed.len() + 1);
+  for (i, item) in removed.into_iter().enumerate() {
+    if i == header_position {
+      moved.extend(trailing_comment.take().map(RootItem::Comment));
+    }
+    moved.push(match item {
+      RootItem::Entry(mut entry) => {
+        let rest = prefix[1..].iter().cloned().chain(std::iter::once(entry.key.first)).chain(entry.key.rest);
+        entry.key = Key {
+          first: prefix[0].clone(),
+          rest: rest.collect(),
+        };
+        RootItem::Entry(entry)
+      }
+      item => item,
+    });
+  }
+  moved.extend(trailing_comment.map(RootItem::Comment));
+
+  // the entries are set apart from any the target section already has, and sit flush beneath its
+  // header otherwise
+  let follows_content = insert_at > target + 1;
+  match moved.first_mut() {
+    Some(RootItem::Comment(comment)) => comment.blank_line_before = follows_content,
+    Some(RootItem::Entry(entry)) => entry.blank_line_before = follows_content,
+    _ => unreachable!("a collapsed section has at least one entry"),
+  }
+
+  let moved_len = moved.len();
+  items.splice(insert_at..insert_at, moved);
+  removed_start + moved_len
+}
+
+// ---- helpers ----
+
+/// The entry ending `unit`, along with the comments before it.
+fn into_moved<'a>(unit: impl Iterator<Item = RootItem<'a>>) -> Moved<'a> {
+  let mut comments = Vec::new();
+  for item in unit {
+    match item {
+      RootItem::Comment(comment) => comments.push(comment),
+      RootItem::Entry(entry) => return Moved { comments, entry },
+      RootItem::TableHeader(_) => unreachable!("a section holds no other header"),
+    }
+  }
+  unreachable!("a moved unit ends with its entry")
+}
+
+/// Writes an entry and its comments into a new section, flush against its header when it is the
+/// first thing there.
+fn push_moved<'a>(unit: Moved<'a>, is_first: bool, items: &mut Vec<RootItem<'a>>) {
+  let Moved { comments, mut entry } = unit;
+  let has_comments = !comments.is_empty();
+  for (i, mut comment) in comments.into_iter().enumerate() {
+    if i == 0 && is_first {
+      comment.blank_line_before = false;
+    }
+    items.push(RootItem::Comment(comment));
+  }
+  if is_first && !has_comments {
+    entry.blank_line_before = false;
+  }
+  items.push(RootItem::Entry(entry));
+}
@@ -0,0 +1,175 @@
+// Rewriting the syntax TOML 1.1 added the way TOML 1.0 spells it, for the `downlevel` option.
+//
+// Most of it is a matter of spelling: `\e` and `\xHH` have `\u` equivalents, and a time without
+// seconds means the same as one whose seconds are zero. An inline table is the exception. The
+// generator already writes one on a single line when targeting TOML 1.0, which leaves its newlines
+// and trailing comma behind, but a single line has nowhere to put the comments within it. So an
+// inline table holding a comment anywhere within it, when it is an entry's whole value, becomes a
+// `[table]` section where each comment can stay beside what it describes -- as does any inline
+// table in that section that holds one in turn. One within an array can only be written inline,
+// so its comments are moved above the entry holding it instead.
+
+use std::borrow::Cow;
+
+use crate::ast::*;
+use crate::tables;
+
+/// Rewrites everything in `root` that only TOML 1.1 can read.
+pub fn apply_downlevel(root: &mut Root) {
+  tables::split_off_entries(root, &is_split_off);
+  let mut items = Vec::with_capacity(root.items.len());
+  for item in std::mem::take(&mut root.items) {
+    match item {
+      RootItem::Entry(entry) if !entry.ignored => items.extend(with_comments_above(entry)),
+      item => items.push(item),
+    }
+  }
+
+  for item in &mut items {
+    match item {
+      RootItem::TableHeader(header) => respell_key(&mut header.key),
+      RootItem::Entry(entry) => respell_entry(entry),
+      RootItem::Comment(_) => {}
+    }
+  }
+  root.items = items;
+}
+
+/// Whether an entry's value is an inline table holding a comment anywhere within it, which it
+/// becomes a section to keep.
+fn is_split_off(entry: &Entry) -> bool {
+  matches!(&entry.value.kind, ValueKind::InlineTable(table) if table.contains_own_comment())
+}
+
+/// The entry preceded by any comments within the inline tables in its value, which are moved out
+/// of it since they could only be written inside a table broken over several lines.
+fn with_comments_above(mut entry: Entry) -> Vec<RootItem> {
+  let mut comments = Vec::new();
+  take_inline_table_comments(&mut entry.value, &mut comments);
+  let mut items = Vec::with_capacity(comments.len() + 1);
+  let mut blank_line_before = entry.blank_line_before;
+  for mut comment in comments {
+    comment.blank_line_before = std::mem::take(&mut blank_line_before);
+    comment.indent_in_source = entry.indent_in_source;
+    items.push(RootItem::Comment(comment));
+  }
+  entry.blank_line_before = blank_line_before;
+  items.push(RootItem::Entry(entry));
+  items
+}
+
+/// Moves the comments out of every inline table within `value`. An array's own comments stay,
+/// since the newlines they bring sit within a value, which TOML 1.0 already allows.
+fn take_inline_table_comments<'a>(value: &mut Value<'a>, comments: &mut Vec<Comment<'a>>) {
+  match &mut value.kind {
+    ValueKind::Array(array) => {
+      for item in array.values.iter_mut().filter(|item| !item.ignored) {
+        take_inline_table_comments(&mut item.value, comments);
+      }
+    }
+    ValueKind::InlineTable(table) => {
+      comments.extend(table.comment_after_open.take());
+      // a `dprint-ignore` directive above an entry has to stay there, and what is within it is kept
+      if table.entries.iter().any(|entry| entry.ignored) {
+        return;
+      }
+      for entry in &mut table.entries {
+        comments.append(&mut entry.leading_comments);
+        take_inline_table_comments(&mut entry.value, comments);
+        comments.extend(entry.trailing_comment.take());
+      }
+      comments.append(&mut table.comments_before_close);
+    }
+    ValueKind::Scalar(_) | ValueKind::MultiLineString(_) => {}
+  }
+}
+
+fn respell_entry(entry: &mut Entry) {
+  respell_key(&mut entry.key);
+  respell_value(&mut entry.value);
+}
+
+fn respell_key(key: &mut Key) {
+  key.first.text = respelled(std::mem::take(&mut key.first.text), without_new_escapes);
+  for part in &mut key.rest {
+    part.text = respelled(std::mem::take(&mut part.text), without_new_escapes);
+  }
+}
+
+fn respell_value(value: &mut Value) {
+  match &mut value.kind {
+    ValueKind::Scalar(text) => {
+      *text = respelled(std::mem::take(text), |text| without_new_escapes(text).or_else(|| with_seconds(text)));
+    }
+    ValueKind::MultiLineString(text) => *text = respelled(std::mem::take(text), without_new_escapes),
+    ValueKind::Array(array) => {
+      for item in &mut array.values {
+        respell_value(&mut item.value);
+      }
+    }
+    ValueKind::InlineTable(table) => {
+      for entry in &mut table.entries {
+        respell_entry(entry);
+      }
+    }
+  }
+}
+
+fn respelled<'a>(text: Cow<'a, str>, respell: impl Fn(&str) -> Option<String>) -> Cow<'a, str> {
+  match respell(&text) {
+    Some(respelled) => Cow::Owned(respelled),
+    None => text,
+  }
+}
+
+/// A basic string with its `\e` and `\xHH` escapes written as the `\u` escapes TOML 1.0 has, or
+/// `None` when there are none to rewrite. A literal string has no escapes at all.
+fn without_new_escapes(text: &str) -> Option<String> {
+  if !text.starts_with('"') || !text.contains('\\') {
+    return None;
+  }
+  let mut result = String::with_capacity(text.len() + 8);
+  let mut changed = false;
+  let mut chars = text.chars();
+  while let Some(c) = chars.next() {
+    result.push(c);
+    if c != '\\' {
+      continue;
+    }
+    // the character after a backslash is never the start of another escape
+    match chars.next() {
+      Some('e') => {
+        result.push_str("u001B");
+        changed = true;
+      }
+      // the two hexadecimal digits follow as they are
+      Some('x') => {
+        result.push_str("u00");
+        changed = true;
+      }
+      Some(c) => result.push(c),
+      None => {}
+    }
+  }
+  changed.then_some(result)
+}
+
+/// A time or date-time whose time has no seconds, with `:00` added, or `None` for any other value.
+/// The parser has already checked the value is well formed, so its shape is all that's looked at.
+fn with_seconds(text: &str) -> Option<String> {
+  let bytes = text.as_bytes();
+  let digits = |start: usize, len: usize| bytes.get(start..start + len).is_some_and(|digits| digits.iter().all(u8::is_ascii_digit));
+  let time_start = if digits(0, 2) && bytes.get(2) == Some(&b':') {
+    0
+  } else if digits(0, 4) && bytes.get(4) == Some(&b'-') && matches!(bytes.get(10), Some(b'T' | b't' | b' ')) {
+    11
+  } else {
+    return None;
+  };
+  // just past the minutes, where the seconds would begin
+  let minutes_end = time_start + 5;
+  if minutes_end > text.len() || bytes.get(minutes_end) == Some(&b':') {
+    return None;
+  }
+  Some(format!("{}:00{}", &text[..minutes_end], &text[minutes_end..]))
+}
@@ -1,16 +1,83 @@
+use crate::parser::Span;
+
 /// The input could not be parsed as TOML.
 ///
-/// The message is a pre-rendered diagnostic that includes the offending line
-/// and a caret pointing at the position of the error.
+/// Displaying the error renders a diagnostic that includes the offending line and a caret pointing
+/// at the position of the error. The parts it is built from are available on their own for a caller
+/// that wants to point at the error itself, such as an editor underlining it.
+///
+/// Parsing carries on past an error, so the first one found also holds any found after it, which
+/// are rendered along with it.
 #[derive(Debug, thiserror::Error)]
-#[error("{message}")]
+#[error("{}", render_all(.rendered, .others))]
 pub struct ParseError {
   message: String,
+  span: Span,
+  original_span: Option<Span>,
+  line: usize,
+  column: usize,
+  rendered: String,
+  others: Box<[ParseError]>,
 }
 
 impl ParseError {
-  pub(crate) fn new(message: String) -> Self {
-    ParseError { message }
+  pub(crate) fn new(message: String, span: Span, original_span: Option<Span>, line: usize, column: usize, rendered: String) -> Self {
+    ParseError {
+      message,
+      span,
+      original_span,
+      line,
+      column,
+      rendered,
+      others: Box::default(),
+    }
+  }
+
+  /// Gives this error the ones found after it.
+  pub(crate) fn with_others(mut self, others: Vec<ParseError>) -> Self {
+    self.others = others.into_boxed_slice();
+    self
+  }
+
+  /// Moves the spans along by `offset` bytes, for text that was parsed with something in front of
+  /// it removed.
+  pub(crate) fn offset_by(mut self, offset: usize) -> Self {
+    self.span = self.span.offset_by(offset);
+    self.original_span = self.original_span.map(|span| span.offset_by(offset));
+    self.others = self.others.into_vec().into_iter().map(|other| other.offset_by(offset)).collect();
+    self
+  }
+
+  /// The error's message on its own, without the position or the source line.
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+
+  /// The byte range of the offending text within the text that was formatted. It may be empty, as
+  /// it is for an error at the end of the input.
+  pub fn span(&self) -> Span {
+    self.span
+  }
+
+  /// For an error about a second definition of something, such as a duplicate key, the byte range
+  /// of the first.
+  pub fn original_span(&self) -> Option<Span> {
+    self.original_span
+  }
+
+  /// The 1-based line the error starts on.
+  pub fn line(&self) -> usize {
+    self.line
+  }
+
+  /// The 1-based column the error starts at, counted in characters.
+  pub fn column(&self) -> usize {
+    self.column
+  }
+
+  /// The errors found after this one, in the order they appear in the file.
+  pub fn others(&self) -> &[ParseError] {
+    &self.others
   }
 }
 
@@ -21,3 +88,20 @@ pub enum FormatError {
   #[error(transparent)]
   Parse(#[from] ParseError),
 }
+
+impl FormatError {
+  /// Every error found while parsing, in the order they appear in the file, so that they can all
+  /// be fixed at once.
+  pub fn parse_errors(&self) -> impl Iterator<Item = &ParseError> {
+    match self {
+      FormatError::Parse(first) => std::iter::once(first).chain(first.others()),
+    }
+  }
+}
+
+fn render_all(rendered: &str, others: &[ParseError]) -> String {
+  std::iter::once(rendered)
+    .chain(others.iter().map(|other| other.rendered.as_str()))
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
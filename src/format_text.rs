@@ -1,13 +1,19 @@
 use super::configuration::Configuration;
+use super::configuration::TomlVersion;
 use super::generation::generate;
 use crate::cargo;
+use crate::dotted_keys;
+use crate::downlevel;
 
 use crate::ast::Root;
 use crate::error::FormatError;
 use crate::error::ParseError;
 use crate::parser;
+use crate::tables;
+use crate::validation;
 
 use dprint_core::configuration::resolve_new_line_kind;
+use dprint_core::formatting::utils::string_utils;
 use dprint_core::formatting::PrintOptions;
 use std::path::Path;
 
@@ -21,11 +27,18 @@ pub fn format_text(file_path: &Path, text: &str, config: &Configuration) -> Resu
 }
 
 fn format_text_inner(file_path: &Path, text: &str, config: &Configuration) -> Result<String, FormatError> {
+  if has_ignore_file_directive(strip_bom(text)) {
+    return Ok(text.to_string());
+  }
+  let bom_len = text.len() - strip_bom(text).len();
   let text = strip_bom(text);
-  let root = parse_and_process_node(file_path, text, config)?;
+  let root = parse_and_process_node(file_path, text, config).map_err(|err| match err {
+    // a caller maps the span back onto the text it passed in, byte order mark and all
+    FormatError::Parse(err) => FormatError::Parse(err.offset_by(bom_len)),
+  })?;
 
   Ok(dprint_core::formatting::format(
-    || generate(&root, config),
+    || generate(&root, text, config),
     config_to_print_options(text, config),
   ))
 }
@@ -34,35 +47,77 @@ fn format_text_inner(file_path: &Path, text: &str, config: &Configuration) -> Re
 pub fn trace_file(file_path: &Path, text: &str, config: &Configuration) -> dprint_core::formatting::TracingResult {
   let root = parse_and_process_node(file_path, text, config).unwrap();
 
-  dprint_core::formatting::trace_printing(|| generate(&root, config), config_to_print_options(text, config))
+  dprint_core::formatting::trace_printing(|| generate(&root, text, config), config_to_print_options(text, config))
 }
 
 fn strip_bom(text: &str) -> &str {
   text.strip_prefix("\u{FEFF}").unwrap_or(text)
 }
 
+/// Whether a `# dprint-ignore-file` comment sits among those the file begins with, before anything
+/// else, which asks for it to be left as it is. It is looked for in the text itself, so that even a
+/// file that fails to parse can opt out.
+fn has_ignore_file_directive(text: &str) -> bool {
+  text
+    .lines()
+    .map(|line| line.trim_matches([' ', '\t', '\r']))
+    .take_while(|line| line.is_empty() || line.starts_with('#'))
+    .any(|line| line.strip_prefix('#').is_some_and(|line| line.trim() == "dprint-ignore-file"))
+}
+
 fn parse_and_process_node<'a>(file_path: &Path, text: &'a str, config: &Configuration) -> Result<Root<'a>, FormatError> {
-  let mut root = parse(text)?;
+  // a file that is to be downleveled may use anything TOML 1.1 allows, which is then rewritten
+  let downlevel = config.downlevel && config.toml_version == TomlVersion::V1_0;
+  let version = if downlevel { TomlVersion::V1_1 } else { config.toml_version };
+  let mut root = parser::parse(text, version).map_err(|errors| to_format_error(&errors, text))?;
+  if config.validate_semantics {
+    validation::validate(&root).map_err(|errors| to_format_error(&errors, text))?;
+  }
+
+  // before sorting, so that the sections it splits off inline tables are sorted like any other
+  if downlevel {
+    downlevel::apply_downlevel(&mut root);
+  }
+
+  // before sorting as well, so that the entries it moves are sorted where they land
+  dotted_keys::apply_dotted_keys(&mut root, config);
+  tables::apply_table_options(&mut root, config);
 
   crate::sorting::apply_sorting(&mut root, config);
 
   // after the general sorting, so that a Cargo.toml keeps its conventional order rather than an
   // alphabetical one
   if config.cargo_apply_conventions && cargo::is_cargo_toml_file(file_path) {
-    cargo::apply_cargo_toml_conventions(&mut root);
+    cargo::apply_cargo_toml_conventions(&mut root, config);
   }
   Ok(root)
 }
 
-fn parse(text: &str) -> Result<Root<'_>, ParseError> {
-  parser::parse(text).map_err(|err| {
-    let (start, end) = highlight_range(&err, text);
-    ParseError::new(dprint_core::formatting::utils::string_utils::format_diagnostic(
-      Some((start, end)),
-      &err.message,
-      text,
-    ))
-  })
+fn to_format_error(errors: &[parser::SyntaxError], text: &str) -> FormatError {
+  let mut errors = errors.iter().map(|err| to_parse_error(err, text));
+  let first = errors.next().expect("at least one error");
+  FormatError::Parse(first.with_others(errors.collect()))
+}
+
+fn to_parse_error(err: &parser::SyntaxError, text: &str) -> ParseError {
+  let mut rendered = format_diagnostic(highlight_range(err.span, text), &err.message, text);
+  // an error about a second definition points back at the first, so both can be found
+  if let Some(original) = err.original {
+    rendered.push_str("\n\n");
+    rendered.push_str(&format_diagnostic(highlight_range(original, text), "first defined here", text));
+  }
+  ParseError::new(
+    err.message.clone(),
+    err.span,
+    err.original,
+    string_utils::get_line_number_of_pos(text, err.span.start),
+    string_utils::get_column_number_of_pos(text, err.span.start),
+    rendered,
+  )
+}
+
+fn format_diagnostic(range: (usize, usize), message: &str, text: &str) -> String {
+  string_utils::format_diagnostic(Some(range), message, text)
 }
 
 /// The range to underline beneath the offending line.
@@ -71,15 +126,15 @@ fn parse(text: &str) -> Result<Root<'_>, ParseError> {
 /// or one that runs past the end of its line — which is exactly what an error at the end of the
 /// input or inside an unterminated string produces. Keeping the range on its own line, and at least
 /// one character wide, means every error gets a caret.
-fn highlight_range(err: &parser::SyntaxError, text: &str) -> (usize, usize) {
-  let line_end = text[err.span.start..].find('\n').map(|i| err.span.start + i).unwrap_or(text.len());
-  let end = err.span.end.min(line_end);
-  if end > err.span.start {
-    (err.span.start, end)
+fn highlight_range(span: parser::Span, text: &str) -> (usize, usize) {
+  let line_end = text[span.start..].find('\n').map(|i| span.start + i).unwrap_or(text.len());
+  let end = span.end.min(line_end);
+  if end > span.start {
+    (span.start, end)
   } else {
     // an empty range still needs something to point at: the character it sits before, or the one
     // it sits after when there is nothing left in the input
-    let start = err.span.start.min(text.len());
+    let start = span.start.min(text.len());
     match text[start..].chars().next() {
       Some(c) => (start, start + c.len_utf8()),
       None => (text[..start].chars().next_back().map(|c| start - c.len_utf8()).unwrap_or(start), start),
@@ -1,6 +1,8 @@
 use crate::configuration::Configuration;
 
 pub struct Context<'a> {
+  /// The text being formatted, which an ignored node is written from.
+  pub text: &'a str,
   pub config: &'a Configuration,
   /// How many single-line inline tables enclose whatever is being generated. A table within one is
   /// kept on a single line too, since a newline between its braces would not be within a value.
@@ -13,8 +15,9 @@ pub struct Context<'a> {
 }
 
 impl<'a> Context<'a> {
-  pub fn new(config: &'a Configuration) -> Self {
+  pub fn new(text: &'a str, config: &'a Configuration) -> Self {
     Self {
+      text,
       config,
       single_line_table_depth: 0,
       collapsed_arrays_depth: 0,
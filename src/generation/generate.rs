@@ -2,17 +2,25 @@ use dprint_core::formatting::conditions::*;
 use dprint_core::formatting::ir_helpers::SingleLineOptions;
 use dprint_core::formatting::*;
 use dprint_core_macros::sc;
+use std::borrow::Cow;
 use std::rc::Rc;
 
 use super::Context;
 use crate::ast::*;
 use crate::configuration::Configuration;
+use crate::configuration::DateTimeSeparator;
 use crate::configuration::IndentKind;
+use crate::configuration::KeyQuoteStyle;
+use crate::configuration::LetterCase;
 use crate::configuration::QuoteStyle;
 use crate::configuration::TrailingCommaKind;
+use crate::parser;
+use crate::strings;
 
-pub fn generate(root: &Root, config: &Configuration) -> PrintItems {
-  let mut context = Context::new(config);
+/// `text` is what `root` was parsed from, which any node a `dprint-ignore` directive covers is
+/// written from as it was.
+pub fn generate(root: &Root, text: &str, config: &Configuration) -> PrintItems {
+  let mut context = Context::new(text, config);
   let mut items = gen_root(root, &mut context);
   items.push_condition(if_true(
     "endOfFileNewLine",
@@ -29,13 +37,21 @@ fn gen_root(root: &Root, context: &mut Context) -> PrintItems {
   for (i, item) in root.items.iter().enumerate() {
     if let Some(previous) = previous {
       items.push_signal(Signal::NewLine);
-      if item.blank_line_before() && allow_blank_line(previous, item) {
-        items.push_signal(Signal::NewLine);
+      if item.blank_line_before() && (item.is_ignored() || allow_blank_line(previous, item)) {
+        for _ in 0..blank_lines_before(item, context.text) {
+          items.push_signal(Signal::NewLine);
+        }
       }
     }
     // the indent starts after the newline that precedes the item, so the writer sees an empty
-    // blank line rather than one padded out with the indentation of what follows it
-    items.extend(ir_helpers::with_indent_times(gen_root_item(item, context), indents[i]));
+    // blank line rather than one padded out with the indentation of what follows it -- and an
+    // ignored item mostly brings along the indentation it was written with
+    let indent = if item.is_ignored() && ignored_line_start(item, context.text).is_some() {
+      0
+    } else {
+      indents[i]
+    };
+    items.extend(ir_helpers::with_indent_times(gen_root_item(item, context), indent));
     previous = Some(item);
   }
   items
@@ -142,6 +158,9 @@ fn allow_blank_line(previous: &RootItem, current: &RootItem) -> bool {
 }
 
 fn gen_root_item(item: &RootItem, context: &mut Context) -> PrintItems {
+  if item.is_ignored() {
+    return gen_ignored_root_item(item, context);
+  }
   match item {
     RootItem::Comment(comment) => gen_comment(comment, context),
     RootItem::Entry(entry) => gen_entry(entry, context),
@@ -149,11 +168,63 @@ fn gen_root_item(item: &RootItem, context: &mut Context) -> PrintItems {
   }
 }
 
+/// Where the line an ignored root item was written on starts, so that it keeps the indentation it
+/// was written with, or `None` for an entry split off an inline table. That began partway through
+/// its line, or was since given an indentation of its own, which it is written at instead.
+fn ignored_line_start(item: &RootItem, text: &str) -> Option<usize> {
+  let start = item.span().start;
+  let before = text[..start].trim_end_matches([' ', '\t']);
+  let starts_line = before.is_empty() || before.ends_with('\n');
+  (starts_line && start - before.len() == item.indent_in_source()).then_some(before.len())
+}
+
+/// How many blank lines to write above a root item that has any: an ignored item keeps as many as
+/// it was written with, where everything else gets one.
+fn blank_lines_before(item: &RootItem, text: &str) -> usize {
+  match ignored_line_start(item, text) {
+    Some(line_start) if item.is_ignored() => text[..line_start].lines().rev().take_while(|line| line.trim().is_empty()).count().max(1),
+    _ => 1,
+  }
+}
+
+/// Writes the source text of a node a `dprint-ignore` directive covers. The lines after its first
+/// keep the indentation they were written with, as a multi-line string's do.
+fn gen_ignored(text: &str) -> PrintItems {
+  let mut items = PrintItems::new();
+  items.push_force_current_line_indentation();
+  items.extend(ir_helpers::gen_from_raw_string(text));
+  items
+}
+
+/// Writes an ignored root item as it was, taking in the indentation before it and the comment beside
+/// it. An entry split off an inline table need not be followed by nothing but its comment, so its
+/// comment is formatted as usual when anything else came between them.
+fn gen_ignored_root_item(item: &RootItem, context: &mut Context) -> PrintItems {
+  let text = context.text;
+  let span = item.span();
+  let start = ignored_line_start(item, text).unwrap_or(span.start);
+  match item.trailing_comment() {
+    Some(comment) if text[span.end..comment.span.start].trim_matches([' ', '\t']).is_empty() => gen_ignored(&text[start..comment.span.end]),
+    Some(comment) => {
+      let mut items = gen_ignored(&text[start..span.end]);
+      items.extend(gen_comment(comment, context));
+      items
+    }
+    None => gen_ignored(&text[start..span.end]),
+  }
+}
+
 fn gen_table_header(header: &TableHeader, context: &mut Context) -> PrintItems {
   // Spec: Naming rules for tables are the same as for keys
   let mut items = PrintItems::new();
   items.push_sc(if header.is_array_of_tables { sc!("[[") } else { sc!("[") });
-  items.extend(gen_key(&header.key));
+  if context.config.table_header_space_surrounding_brackets {
+    items.push_sc(sc!(" "));
+  }
+  items.extend(gen_key(&header.key, context));
+  if context.config.table_header_space_surrounding_brackets {
+    items.push_sc(sc!(" "));
+  }
   items.push_sc(if header.is_array_of_tables { sc!("]]") } else { sc!("]") });
   if let Some(comment) = &header.trailing_comment {
     items.extend(gen_comment(comment, context));
@@ -170,28 +241,50 @@ fn gen_entry(entry: &Entry, context: &mut Context) -> PrintItems {
 }
 
 fn gen_entry_without_trailing_comment(entry: &Entry, context: &mut Context) -> PrintItems {
-  let mut items = gen_key(&entry.key);
+  let mut items = gen_key(&entry.key, context);
   items.push_sc(if context.config.space_surrounding_equals { sc!(" = ") } else { sc!("=") });
   items.extend(gen_value(&entry.value, context));
   items
 }
 
 /// Spec: A key may be either bare, quoted, or dotted.
-fn gen_key(key: &Key) -> PrintItems {
+fn gen_key(key: &Key, context: &mut Context) -> PrintItems {
   let mut items = PrintItems::new();
   for (i, part) in key.parts().enumerate() {
     if i > 0 {
-      items.push_sc(sc!("."));
+      items.push_sc(if context.config.key_space_surrounding_dots { sc!(" . ") } else { sc!(".") });
+    }
+    match requoted_key_part(&part.text, context.config.key_quote_style) {
+      Some(text) => items.extend(ir_helpers::gen_from_string(&text)),
+      None => items.extend(ir_helpers::gen_from_string(&part.text)),
     }
-    items.extend(ir_helpers::gen_from_string(part.text));
   }
   items
 }
 
+/// The text of a key segment rewritten in the preferred style, or `None` when it is already
+/// written that way. The segment names the same key either way: a quoted one is decoded, and only
+/// written bare when every character of its name is allowed in a bare key.
+fn requoted_key_part(text: &str, style: KeyQuoteStyle) -> Option<String> {
+  if style == KeyQuoteStyle::Maintain {
+    return None;
+  }
+  // a bare segment is already as plain as it gets
+  let name = strings::string_value(text)?;
+  if !name.is_empty() && name.chars().all(parser::is_bare_key_char) {
+    return Some(name.into_owned());
+  }
+  match style {
+    KeyQuoteStyle::BareOrDouble if text.starts_with('\'') => Some(strings::to_basic_string(&name)),
+    KeyQuoteStyle::BareOrSingle if text.starts_with('"') => strings::to_literal_string(&name),
+    _ => None,
+  }
+}
+
 /// Spec: Values must be either String, Integer, Float, Boolean, DateTimes, Array, InlineTable
 fn gen_value(value: &Value, context: &mut Context) -> PrintItems {
   match &value.kind {
-    ValueKind::Scalar(text) => match requoted_string(text, context.config.quote_style) {
+    ValueKind::Scalar(text) => match formatted_scalar(text, context.config) {
       Some(text) => ir_helpers::gen_from_string(&text),
       None => ir_helpers::gen_from_string(text),
     },
@@ -206,19 +299,48 @@ fn gen_value(value: &Value, context: &mut Context) -> PrintItems {
   }
 }
 
+/// The text of a scalar rewritten as the options ask, or `None` when it is already written that way.
+fn formatted_scalar(text: &str, config: &Configuration) -> Option<String> {
+  if !text.starts_with(['"', '\'']) {
+    return formatted_number(text, config).or_else(|| formatted_date_time(text, config));
+  }
+  // escapes are normalized before the quote is chosen, since doing away with one may let the
+  // string be requoted, and again after, for a string only just written as a basic one
+  let normalized = normalized_escapes(text, config);
+  let text = normalized.as_deref().unwrap_or(text);
+  match requoted_string(text, config.quote_style) {
+    // a literal string would hold as itself a character normalizing means to keep escaped
+    Some(requoted) if config.escapes_normalize && requoted.starts_with('\'') && strings::has_invisible(&requoted) => normalized,
+    Some(requoted) => Some(normalized_escapes(&requoted, config).unwrap_or(requoted)),
+    None => normalized,
+  }
+}
+
+fn normalized_escapes(text: &str, config: &Configuration) -> Option<String> {
+  if config.escapes_normalize {
+    strings::normalized_escapes(text, config.escapes_hex_digit_case, config.toml_version)
+  } else {
+    None
+  }
+}
+
 /// The text of a single-line string rewritten with the preferred quote, or `None` when it is
 /// already written with it or cannot be rewritten.
 ///
 /// A basic string reads a backslash as beginning an escape where a literal string reads it as
-/// itself, so the two spell the same value differently as soon as one appears. Rewriting such a
-/// string means rewriting its contents, and rewriting one that already holds the preferred quote
-/// means escaping that quote — neither of which is done here. The point is to settle on one quote
-/// wherever it costs nothing, not to make every string look the same.
+/// itself, so the two spell the same value differently as soon as one appears. The `prefer` styles
+/// leave such a string alone, along with one that already holds the preferred quote, rather than
+/// rewrite its contents: the point is to settle on one quote wherever it costs nothing, not to make
+/// every string look the same. The `always` styles are for when it should, so they decode the
+/// string and encode its value again with the other quote.
 fn requoted_string(text: &str, style: QuoteStyle) -> Option<String> {
   let (from, to) = match style {
     QuoteStyle::Maintain => return None,
     QuoteStyle::PreferDouble => ('\'', '"'),
     QuoteStyle::PreferSingle => ('"', '\''),
+    QuoteStyle::AlwaysDouble if text.starts_with('\'') => return strings::string_value(text).map(|value| strings::to_basic_string(&value)),
+    QuoteStyle::AlwaysSingle if text.starts_with('"') => return strings::string_value(text).and_then(|value| strings::to_literal_string(&value)),
+    QuoteStyle::AlwaysDouble | QuoteStyle::AlwaysSingle => return None,
   };
   let inner = text.strip_prefix(from)?.strip_suffix(from)?;
   if inner.contains('\\') || inner.contains(to) {
@@ -231,6 +353,140 @@ fn requoted_string(text: &str, style: QuoteStyle) -> Option<String> {
   Some(result)
 }
 
+// ---- numbers ----
+
+/// The text of a number rewritten as the `numbers` options ask, or `None` when it is not a number
+/// or is already written that way. Only the spelling changes, never the value.
+fn formatted_number(text: &str, config: &Configuration) -> Option<String> {
+  let starts_like_number = text.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') || text == "inf" || text == "nan";
+  if !starts_like_number || parser::is_date_time_shaped(text) {
+    return None;
+  }
+  let (sign, unsigned) = match text.strip_prefix('+') {
+    Some(unsigned) if config.numbers_remove_plus_sign => ("", unsigned),
+    Some(unsigned) => ("+", unsigned),
+    None => match text.strip_prefix('-') {
+      Some(unsigned) => ("-", unsigned),
+      None => ("", text),
+    },
+  };
+
+  let mut result = String::with_capacity(text.len() + 4);
+  result.push_str(sign);
+  if let Some(digits) = unsigned.strip_prefix("0x") {
+    result.push_str("0x");
+    let digits = with_case(digits, config.numbers_hex_digit_case);
+    result.push_str(&with_digit_separators(&digits, 4, config));
+  } else if let Some(digits) = unsigned.strip_prefix("0b") {
+    result.push_str("0b");
+    result.push_str(&with_digit_separators(digits, 4, config));
+  } else if unsigned.starts_with("0o") || unsigned == "inf" || unsigned == "nan" {
+    result.push_str(unsigned);
+  } else {
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+      Some(index) => (&unsigned[..index], Some(&unsigned[index + 1..])),
+      None => (unsigned, None),
+    };
+    match exponent {
+      // only an integer is given separators; a float's digits are left as written
+      None if !mantissa.contains('.') => result.push_str(&with_digit_separators(mantissa, 3, config)),
+      _ => result.push_str(mantissa),
+    }
+    if let Some(exponent) = exponent {
+      let letter = &unsigned[mantissa.len()..mantissa.len() + 1];
+      result.push_str(&with_case(letter, config.numbers_exponent_case));
+      match exponent.strip_prefix('+') {
+        Some(digits) if config.numbers_remove_plus_sign => result.push_str(digits),
+        _ => result.push_str(exponent),
+      }
+    }
+  }
+  (result != text).then_some(result)
+}
+
+fn with_case(text: &str, case: LetterCase) -> Cow<'_, str> {
+  match case {
+    LetterCase::Maintain => Cow::Borrowed(text),
+    LetterCase::Lower => Cow::Owned(text.to_ascii_lowercase()),
+    LetterCase::Upper => Cow::Owned(text.to_ascii_uppercase()),
+  }
+}
+
+/// The digits of an integer separated into groups of `group_len` from the right, when the
+/// `numbers.digitSeparators` option asks for it. Digits the author already separated are left as
+/// they are, as is an integer short enough to read at a glance.
+fn with_digit_separators<'a>(digits: &'a str, group_len: usize, config: &Configuration) -> Cow<'a, str> {
+  if !config.numbers_digit_separators || digits.len() <= 4 || digits.contains('_') {
+    return Cow::Borrowed(digits);
+  }
+  let mut result = String::with_capacity(digits.len() + digits.len() / group_len);
+  for (i, c) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i).is_multiple_of(group_len) {
+      result.push('_');
+    }
+    result.push(c);
+  }
+  Cow::Owned(result)
+}
+
+// ---- date-times ----
+
+/// The text of a date-time rewritten as the `dateTime` options ask, or `None` when it is not a
+/// date-time or is already written that way. The parser has already checked it is well formed, so
+/// only its shape is looked at here.
+fn formatted_date_time(text: &str, config: &Configuration) -> Option<String> {
+  if !parser::is_date_time_shaped(text) {
+    return None;
+  }
+  let mut result = String::with_capacity(text.len() + 4);
+  // a date is always `YYYY-MM-DD`, and any time follows a one character separator
+  let time = if text.as_bytes()[4] == b'-' {
+    result.push_str(&text[..10]);
+    match text[10..].chars().next() {
+      Some(separator) => {
+        result.push(match config.date_time_separator {
+          DateTimeSeparator::Maintain => separator,
+          DateTimeSeparator::T => 'T',
+          DateTimeSeparator::Space => ' ',
+        });
+        &text[11..]
+      }
+      None => "",
+    }
+  } else {
+    text
+  };
+
+  if !time.is_empty() {
+    // `HH:MM`, then the seconds, which TOML 1.1 lets a time go without
+    let seconds_end = if time[5..].starts_with(':') { 8 } else { 5 };
+    result.push_str(&time[..seconds_end]);
+    let rest = &time[seconds_end..];
+    let (fraction, offset) = match rest.strip_prefix('.') {
+      Some(after_point) => {
+        let digits_len = after_point.find(|c: char| !c.is_ascii_digit()).unwrap_or(after_point.len());
+        (&after_point[..digits_len], &after_point[digits_len..])
+      }
+      None => ("", rest),
+    };
+    if seconds_end == 8 {
+      let fraction = if config.date_time_trim_fractional_seconds {
+        fraction.trim_end_matches('0')
+      } else {
+        fraction
+      };
+      let min_len = usize::from(config.date_time_pad_fractional_seconds);
+      if !fraction.is_empty() || min_len > 0 {
+        result.push('.');
+        result.push_str(fraction);
+        result.extend(std::iter::repeat_n('0', min_len.saturating_sub(fraction.len())));
+      }
+    }
+    result.push_str(&with_case(offset, config.date_time_offset_case));
+  }
+  (result != text).then_some(result)
+}
+
 // ---- arrays ----
 
 fn gen_array(array: &Array, context: &mut Context) -> PrintItems {
@@ -311,11 +567,11 @@ fn gen_array(array: &Array, context: &mut Context) -> PrintItems {
 
 fn gen_inline_table(table: &InlineTable, context: &mut Context) -> PrintItems {
   // TOML 1.1 allows an inline table to be written over several lines. The author chose that, so
-  // keep it; a table written on one line is never expanded, which would produce syntax a 1.0 parser
-  // rejects. A table holding a comment of its own is multi-line whatever follows its brace, since a
-  // comment runs to the end of its line, and generating it on one line would drop the comment. A
-  // comment inside one of its values is not the table's, and expanding the table over it would turn
-  // a 1.0 document into a 1.1 one.
+  // keep it, unless the file targets TOML 1.0; a table written on one line is never expanded, which
+  // would produce syntax a 1.0 parser rejects. A table holding a comment of its own is multi-line
+  // whatever follows its brace, since a comment runs to the end of its line, and generating it on
+  // one line would drop the comment. A comment inside one of its values is not the table's, and
+  // expanding the table over it would turn a 1.0 document into a 1.1 one.
   //
   // Nothing within a table that has to stay on one line may break, so a table nested in one is
   // generated on a single line however the author wrote it.
@@ -411,6 +667,8 @@ struct SeparatedItem<'a> {
   blank_line_before_item: bool,
   /// Whether the item is written over more than one line however it is formatted.
   is_known_multi_line: bool,
+  /// Where the item was written, when a `dprint-ignore` directive asks for it to be written as it was.
+  ignored_span: Option<parser::Span>,
   entry: SeparatedItemValue<'a>,
 }
 
@@ -422,35 +680,48 @@ enum SeparatedItemValue<'a> {
 /// Whether a value is written over several lines depends on the configuration, so this stands in
 /// for the `From` conversion the item would otherwise be built by.
 trait IntoSeparatedItem<'a> {
-  fn into_separated_item(self, config: &Configuration, line_context: LineContext) -> SeparatedItem<'a>;
+  fn into_separated_item(self, text: &str, config: &Configuration, line_context: LineContext) -> SeparatedItem<'a>;
 }
 
 impl<'a> IntoSeparatedItem<'a> for &'a ArrayValue<'a> {
-  fn into_separated_item(self, config: &Configuration, line_context: LineContext) -> SeparatedItem<'a> {
+  fn into_separated_item(self, text: &str, config: &Configuration, line_context: LineContext) -> SeparatedItem<'a> {
+    let ignored_span = self.ignored.then_some(self.value.span);
     SeparatedItem {
       leading_comments: &self.leading_comments,
       blank_line_before: self.blank_line_before,
       trailing_comment: self.trailing_comment.as_ref(),
       blank_line_before_item: blank_line_before_item(&self.leading_comments, self.blank_line_before),
-      is_known_multi_line: self.value.is_known_multi_line(config, line_context),
+      is_known_multi_line: is_known_multi_line(ignored_span, text, &self.value, config, line_context),
+      ignored_span,
       entry: SeparatedItemValue::Value(&self.value),
     }
   }
 }
 
 impl<'a> IntoSeparatedItem<'a> for &'a Entry<'a> {
-  fn into_separated_item(self, config: &Configuration, line_context: LineContext) -> SeparatedItem<'a> {
+  fn into_separated_item(self, text: &str, config: &Configuration, line_context: LineContext) -> SeparatedItem<'a> {
+    let ignored_span = self.ignored.then(|| self.span());
     SeparatedItem {
       leading_comments: &self.leading_comments,
       blank_line_before: self.blank_line_before,
       trailing_comment: self.trailing_comment.as_ref(),
       blank_line_before_item: blank_line_before_item(&self.leading_comments, self.blank_line_before),
-      is_known_multi_line: self.value.is_known_multi_line(config, line_context),
+      is_known_multi_line: is_known_multi_line(ignored_span, text, &self.value, config, line_context),
+      ignored_span,
       entry: SeparatedItemValue::Entry(self),
     }
   }
 }
 
+/// Whether an item is written over several lines, which for one written as it was is down to
+/// whether it was written that way.
+fn is_known_multi_line(ignored_span: Option<parser::Span>, text: &str, value: &Value, config: &Configuration, line_context: LineContext) -> bool {
+  match ignored_span {
+    Some(span) => text[span.start..span.end].contains('\n'),
+    None => value.is_known_multi_line(config, line_context),
+  }
+}
+
 /// A blank line above an item sits above its comments when it has any.
 fn blank_line_before_item(leading_comments: &[Comment<'_>], blank_line_before: bool) -> bool {
   match leading_comments.first() {
@@ -468,6 +739,7 @@ where
   let indent_width = context.config.indent_width;
   let trailing_commas = context.config.trailing_commas;
   let line_context = context.line_context();
+  let text = context.text;
   ir_helpers::gen_separated_values(
     |is_multi_line_ref| {
       let count = items.len();
@@ -476,7 +748,11 @@ where
       // author asked for one, and the item may since have been moved by the Cargo.toml sorting,
       // which would leave any position taken from the source pointing at the wrong line.
       let mut line = 0;
-      for (i, item) in items.iter().map(|item| item.into_separated_item(context.config, line_context)).enumerate() {
+      for (i, item) in items
+        .iter()
+        .map(|item| item.into_separated_item(text, context.config, line_context))
+        .enumerate()
+      {
         if i > 0 {
           line += if item.blank_line_before_item { 2 } else { 1 };
         }
@@ -539,9 +815,10 @@ fn gen_separated_item(item: SeparatedItem, generated_comma: PrintItems, context:
     items.push_signal(Signal::NewLine);
   }
 
-  items.extend(match item.entry {
-    SeparatedItemValue::Value(value) => gen_value(value, context),
-    SeparatedItemValue::Entry(entry) => gen_entry_without_trailing_comment(entry, context),
+  items.extend(match (item.ignored_span, item.entry) {
+    (Some(span), _) => gen_ignored(&context.text[span.start..span.end]),
+    (None, SeparatedItemValue::Value(value)) => gen_value(value, context),
+    (None, SeparatedItemValue::Entry(entry)) => gen_entry_without_trailing_comment(entry, context),
   });
   items.extend(generated_comma);
 
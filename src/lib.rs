@@ -1,15 +1,21 @@
 mod ast;
 mod cargo;
 pub mod configuration;
+mod dotted_keys;
+mod downlevel;
 mod error;
 mod format_text;
 mod generation;
 mod parser;
 mod sorting;
+mod strings;
+mod tables;
+mod validation;
 
 pub use error::FormatError;
 pub use error::ParseError;
 pub use format_text::format_text;
+pub use parser::Span;
 
 #[cfg(feature = "tracing")]
 pub use format_text::trace_file;
@@ -1,17 +1,21 @@
 // A hand written TOML parser producing the lossless tree in [`crate::ast`].
 //
 // It accepts TOML 1.1, which over 1.0 adds newlines and trailing commas in inline tables, the
-// `\xHH` and `\e` escapes, and optional seconds in times. Whether any of that is *emitted* is the
-// formatter's decision, not the parser's.
+// `\xHH` and `\e` escapes, and optional seconds in times, unless the `tomlVersion` option targets
+// 1.0, in which case each of those is an error. Whether any of it is *emitted* is the formatter's
+// decision, not the parser's.
 //
-// Values are never interpreted, only delimited: the formatter reproduces them verbatim, so the
-// parser's job for a value is to find where it ends. That keeps number, date-time and string
-// handling to a scan rather than a full decode.
+// Values are never interpreted, only delimited and checked: the formatter reproduces them verbatim,
+// so the parser's job for a value is to find where it ends and that it is one TOML would accept.
+// That keeps number, date-time and string handling to a scan rather than a full decode.
+
+use std::ops::Range;
 
 use crate::ast::*;
+use crate::configuration::TomlVersion;
 
 /// A byte-index range into the text being parsed.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Span {
   pub start: usize,
   pub end: usize,
@@ -21,6 +25,10 @@ impl Span {
   pub fn new(start: usize, end: usize) -> Self {
     Span { start, end }
   }
+
+  pub(crate) fn offset_by(self, offset: usize) -> Self {
+    Span::new(self.start + offset, self.end + offset)
+  }
 }
 
 /// A syntax error, with the span of the offending text.
@@ -28,11 +36,22 @@ impl Span {
 pub struct SyntaxError {
   pub message: String,
   pub span: Span,
+  /// The earlier definition an error about a second one conflicts with, such as the first of two
+  /// entries sharing a key.
+  pub original: Option<Span>,
 }
 
-/// Parses TOML text into a [`Root`].
-pub fn parse(text: &str) -> Result<Root<'_>, SyntaxError> {
-  let mut parser = Parser { text, pos: 0, depth: 0 };
+/// Parses TOML text written for the given version into a [`Root`], or reports every syntax error
+/// found in it.
+pub fn parse(text: &str, version: TomlVersion) -> Result<Root<'_>, Vec<SyntaxError>> {
+  let mut parser = Parser {
+    text,
+    pos: 0,
+    depth: 0,
+    version,
+    open_at_error: 0,
+    string_open_at_error: None,
+  };
   parser.parse_root()
 }
 
@@ -41,10 +60,33 @@ fn is_value_terminator(c: char) -> bool {
   matches!(c, ' ' | '\t' | '\r' | '\n' | ',' | ']' | '}' | '#' | '=')
 }
 
-fn is_bare_key_char(c: char) -> bool {
+pub(crate) fn is_bare_key_char(c: char) -> bool {
   c.is_ascii_alphanumeric() || c == '_' || c == '-'
 }
 
+/// Whether the line beginning `text` reads as a table header rather than, say, a line of an array
+/// of arrays: a bracket holding something that could be a key, which a value like `[1, 2]` can't be.
+/// One missing its closing bracket still counts, as that's an error of its own to report.
+fn looks_like_table_header(text: &str) -> bool {
+  let Some(rest) = text.trim_start_matches([' ', '\t']).strip_prefix('[') else {
+    return false;
+  };
+  let mut chars = rest.strip_prefix('[').unwrap_or(rest).chars();
+  while let Some(c) = chars.next() {
+    match c {
+      ']' | '\n' | '\r' | '#' => return true,
+      '"' | '\'' => {
+        if !chars.by_ref().take_while(|&next| next != '\n').any(|next| next == c) {
+          return false;
+        }
+      }
+      c if is_bare_key_char(c) || matches!(c, '.' | ' ' | '\t') => {}
+      _ => return false,
+    }
+  }
+  true
+}
+
 /// How deeply arrays and inline tables may be nested within one another.
 ///
 /// Each level costs a stack frame, and running out of stack aborts the process rather than raising
@@ -58,6 +100,15 @@ struct Parser<'a> {
   pos: usize,
   /// How many arrays and inline tables enclose the value being parsed.
   depth: usize,
+  /// The version of TOML the text is read as, which decides whether TOML 1.1's additions are let
+  /// through.
+  version: TomlVersion,
+  /// How many arrays and inline tables were open when the latest error was raised, which recovery
+  /// has to see closed again before it can carry on.
+  open_at_error: usize,
+  /// The quote of the multi-line string the latest error was raised within, whose remaining lines
+  /// recovery has to pass over rather than read as items.
+  string_open_at_error: Option<char>,
 }
 
 impl<'a> Parser<'a> {
@@ -105,7 +156,11 @@ impl<'a> Parser<'a> {
   }
 
   fn error<T>(&self, message: impl Into<String>, span: Span) -> Result<T, SyntaxError> {
-    Err(SyntaxError { message: message.into(), span })
+    Err(SyntaxError {
+      message: message.into(),
+      span,
+      original: None,
+    })
   }
 
   fn error_here<T>(&self, message: impl Into<String>) -> Result<T, SyntaxError> {
@@ -127,12 +182,15 @@ impl<'a> Parser<'a> {
       }
       self.bump();
     }
+    // trailing spaces and tabs on a comment line are insignificant, but `trim_end` would also
+    // take Unicode whitespace such as a no-break space, which is legitimate comment content
+    let text = self.text[start..self.pos].trim_end_matches([' ', '\t']);
     Comment {
-      // trailing spaces and tabs on a comment line are insignificant, but `trim_end` would also
-      // take Unicode whitespace such as a no-break space, which is legitimate comment content
-      text: self.text[start..self.pos].trim_end_matches([' ', '\t']),
+      text,
       blank_line_before,
       indent_in_source,
+      span: Span::new(start, start + text.len()),
+      ignored: false,
     }
   }
 
@@ -146,11 +204,28 @@ impl<'a> Parser<'a> {
     }
   }
 
+  /// Consumes spaces and, if a comment follows within an inline table, parses it. Only TOML 1.1
+  /// allows one there, since it runs to the end of the line.
+  fn parse_inline_table_comment(&mut self) -> Result<Option<Comment<'a>>, SyntaxError> {
+    self.skip_spaces();
+    if self.version == TomlVersion::V1_0 && self.peek() == Some('#') {
+      return self.error_here("a comment within an inline table requires TOML 1.1");
+    }
+    Ok(self.parse_trailing_comment())
+  }
+
   // ---- root ----
 
-  fn parse_root(&mut self) -> Result<Root<'a>, SyntaxError> {
+  /// Parses the whole document, carrying on past an error so that every error in the file is
+  /// reported at once rather than one per run.
+  fn parse_root(&mut self) -> Result<Root<'a>, Vec<SyntaxError>> {
     let mut items = Vec::new();
+    let mut errors = Vec::new();
     let mut newlines = 0usize;
+    // whether the items are within a `# dprint-ignore-start` range, and whether the one just parsed
+    // was a `# dprint-ignore` directive for the next
+    let mut in_ignored_range = false;
+    let mut ignore_next = false;
     loop {
       // the whitespace skipped here is what precedes the item on its own line, since every path
       // that doesn't finish a line comes back around to the top of the loop
@@ -164,36 +239,145 @@ impl<'a> Parser<'a> {
         newlines += 1;
         continue;
       }
-      if self.peek() == Some('\r') {
-        return self.error_here("expected a line feed after the carriage return");
-      }
 
       // the newline that ends the previous item's line counts as one, so a blank line is two
       let blank_line_before = !items.is_empty() && newlines >= 2;
       newlines = 0;
 
-      match self.peek() {
-        Some('#') => {
-          items.push(RootItem::Comment(self.parse_comment(blank_line_before, indent_in_source)));
-          continue;
+      match self.parse_root_item(blank_line_before, indent_in_source) {
+        Ok(mut item) => {
+          let directive = |item: &RootItem, name| matches!(item, RootItem::Comment(comment) if comment.is_directive(name));
+          if in_ignored_range && directive(&item, "dprint-ignore-end") {
+            in_ignored_range = false;
+          } else if in_ignored_range {
+            item.set_ignored(true);
+          } else if directive(&item, "dprint-ignore-start") {
+            in_ignored_range = true;
+          } else if ignore_next && !item.blank_line_before() && !matches!(item, RootItem::Comment(_)) {
+            item.set_ignored(true);
+          }
+          ignore_next = !in_ignored_range && directive(&item, "dprint-ignore");
+          items.push(item);
         }
-        Some('[') => items.push(RootItem::TableHeader(self.parse_table_header(blank_line_before, indent_in_source)?)),
-        _ => {
-          let mut entry = self.parse_entry(blank_line_before, Vec::new(), indent_in_source)?;
-          entry.trailing_comment = self.parse_trailing_comment();
-          items.push(RootItem::Entry(entry));
+        Err(error) => {
+          errors.push(error);
+          self.recover();
         }
       }
+    }
+    if errors.is_empty() {
+      Ok(Root { items })
+    } else {
+      Err(errors)
+    }
+  }
 
-      // an item and its trailing comment have to be the last thing on their line
-      if !self.is_eof() && !matches!(self.peek(), Some('\n') | Some('\r')) {
-        return self.error_here("expected a newline");
+  /// Parses the item beginning at the current position, which is the first thing on its line.
+  fn parse_root_item(&mut self, blank_line_before: bool, indent_in_source: usize) -> Result<RootItem<'a>, SyntaxError> {
+    let item = match self.peek() {
+      // a comment runs to the end of its line, so nothing can follow it there
+      Some('#') => return Ok(RootItem::Comment(self.parse_comment(blank_line_before, indent_in_source))),
+      Some('\r') => return self.error_here("expected a line feed after the carriage return"),
+      Some('[') => RootItem::TableHeader(self.parse_table_header(blank_line_before, indent_in_source)?),
+      _ => {
+        let mut entry = self.parse_entry(blank_line_before, Vec::new(), indent_in_source)?;
+        entry.trailing_comment = self.parse_trailing_comment();
+        RootItem::Entry(entry)
       }
+    };
+
+    // an item and its trailing comment have to be the last thing on their line
+    if !self.is_eof() && !matches!(self.peek(), Some('\n') | Some('\r')) {
+      return self.error_here("expected a newline");
     }
-    Ok(Root { items })
+    Ok(item)
+  }
+
+  /// Moves past an item that failed to parse, to where the next one can be tried.
+  ///
+  /// That is normally the next line. An error partway through an array or inline table that goes
+  /// on over more lines, though, would leave the rest of its lines to be read as items of their own,
+  /// each of which would be reported too. So when the collections open at the error aren't closed
+  /// again on its line, everything up to the next table header is passed over as well. An error
+  /// within a multi-line string first moves past the end of the string, as its lines aren't items.
+  fn recover(&mut self) {
+    if let Some(quote) = self.string_open_at_error.take() {
+      self.skip_multi_line_string_rest(quote);
+    }
+    let open = std::mem::take(&mut self.open_at_error);
+    let closed = open == 0 || self.closes_on_line(open);
+    self.skip_line();
+    if !closed {
+      while !self.is_eof() && !looks_like_table_header(self.rest()) {
+        self.skip_line();
+      }
+    }
+  }
+
+  /// Whether `open` collections are all closed again before the end of the current line, passing
+  /// over any strings along the way.
+  fn closes_on_line(&self, mut open: usize) -> bool {
+    let mut chars = self.rest().chars();
+    while let Some(c) = chars.next() {
+      match c {
+        '\n' | '#' => return false,
+        '[' | '{' => open += 1,
+        ']' | '}' => {
+          open -= 1;
+          if open == 0 {
+            return true;
+          }
+        }
+        '"' | '\'' => {
+          let quote = c;
+          let mut escaped = false;
+          for c in chars.by_ref() {
+            match c {
+              '\n' => return false,
+              _ if escaped => escaped = false,
+              '\\' if quote == '"' => escaped = true,
+              _ if c == quote => break,
+              _ => {}
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+    false
+  }
+
+  /// Moves past the delimiter that closes the multi-line string quoted with `quote`, or to the end
+  /// of the input when nothing does.
+  fn skip_multi_line_string_rest(&mut self, quote: char) {
+    let mut chars = self.rest().char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+      if c == '\\' && quote == '"' {
+        chars.next();
+      } else if c == quote {
+        let mut end = index + 1;
+        while let Some((index, _)) = chars.next_if(|&(_, next)| next == quote) {
+          end = index + 1;
+        }
+        if end - index >= 3 {
+          self.pos += end;
+          return;
+        }
+      }
+    }
+    self.pos = self.text.len();
+  }
+
+  /// Moves to the start of the next line, or to the end of the input when there isn't one.
+  fn skip_line(&mut self) {
+    self.pos = match self.rest().find('\n') {
+      Some(index) => self.pos + index + 1,
+      None => self.text.len(),
+    };
   }
 
   fn parse_table_header(&mut self, blank_line_before: bool, indent_in_source: usize) -> Result<TableHeader<'a>, SyntaxError> {
+    let start = self.pos;
     self.bump(); // '['
     let is_array_of_tables = self.peek() == Some('[');
     if is_array_of_tables {
@@ -214,8 +398,10 @@ impl<'a> Parser<'a> {
       key,
       is_array_of_tables,
       blank_line_before,
+      span: Span::new(start, self.pos),
       trailing_comment: self.parse_trailing_comment(),
       indent_in_source,
+      ignored: false,
     })
   }
 
@@ -229,6 +415,8 @@ impl<'a> Parser<'a> {
     self.skip_spaces();
     let value = self.parse_value()?;
 
+    // only an entry within an inline table has its comments to hand; the root sets its own
+    let ignored = !blank_line_before && leading_comments.last().is_some_and(|comment| comment.is_directive("dprint-ignore"));
     Ok(Entry {
       key,
       value,
@@ -236,6 +424,7 @@ impl<'a> Parser<'a> {
       trailing_comment: None, // filled in by the caller, which knows where the line ends
       leading_comments,
       indent_in_source,
+      ignored,
     })
   }
 
@@ -274,7 +463,8 @@ impl<'a> Parser<'a> {
       _ => return self.error_here("expected a key"),
     }
     Ok(KeyPart {
-      text: &self.text[start..self.pos],
+      text: self.text[start..self.pos].into(),
+      span: Span::new(start, self.pos),
     })
   }
 
@@ -285,6 +475,10 @@ impl<'a> Parser<'a> {
     }
     self.depth += 1;
     let result = parse(self);
+    // the innermost collection is the first to see the error on its way out
+    if result.is_err() && self.open_at_error == 0 {
+      self.open_at_error = self.depth;
+    }
     self.depth -= 1;
     result
   }
@@ -293,17 +487,9 @@ impl<'a> Parser<'a> {
 
   fn parse_value(&mut self) -> Result<Value<'a>, SyntaxError> {
     let start = self.pos;
-    match self.peek() {
-      Some('[') => {
-        let array = self.parse_nested(Self::parse_array)?;
-        Ok(Value { kind: ValueKind::Array(array) })
-      }
-      Some('{') => {
-        let table = self.parse_nested(Self::parse_inline_table)?;
-        Ok(Value {
-          kind: ValueKind::InlineTable(table),
-        })
-      }
+    let kind = match self.peek() {
+      Some('[') => ValueKind::Array(self.parse_nested(Self::parse_array)?),
+      Some('{') => ValueKind::InlineTable(self.parse_nested(Self::parse_inline_table)?),
       Some('"') | Some('\'') => {
         let is_multi_line = self.starts_with("\"\"\"") || self.starts_with("'''");
         if self.peek() == Some('"') {
@@ -312,22 +498,22 @@ impl<'a> Parser<'a> {
           self.scan_literal_string()?;
         }
         let text = &self.text[start..self.pos];
-        Ok(Value {
-          kind: if is_multi_line {
-            ValueKind::MultiLineString(text)
-          } else {
-            ValueKind::Scalar(text)
-          },
-        })
+        if is_multi_line {
+          ValueKind::MultiLineString(text.into())
+        } else {
+          ValueKind::Scalar(text.into())
+        }
       }
       Some(_) => {
         self.scan_bare_value()?;
-        Ok(Value {
-          kind: ValueKind::Scalar(&self.text[start..self.pos]),
-        })
+        ValueKind::Scalar(self.text[start..self.pos].into())
       }
-      None => self.error_here("expected a value"),
-    }
+      None => return self.error_here("expected a value"),
+    };
+    Ok(Value {
+      kind,
+      span: Span::new(start, self.pos),
+    })
   }
 
   /// Scans an unquoted value: a number, boolean or date-time.
@@ -358,10 +544,16 @@ impl<'a> Parser<'a> {
       }
     }
 
-    if !is_bare_value_shaped(&self.text[start..self.pos]) {
-      return self.error("expected a value", Span::new(start, self.pos));
+    let token = &self.text[start..self.pos];
+    let result = if is_date_time_shaped(token) {
+      check_date_time(token, self.version)
+    } else {
+      check_number_or_boolean(token)
+    };
+    match result {
+      Ok(()) => Ok(()),
+      Err(error) => self.error(error.message, Span::new(start + error.range.start, start + error.range.end)),
     }
-    Ok(())
   }
 
   // ---- strings ----
@@ -378,14 +570,14 @@ impl<'a> Parser<'a> {
       match self.peek() {
         None | Some('\n') | Some('\r') => return self.error("unterminated string", Span::new(start, self.pos)),
         Some('\\') => {
-          self.bump();
-          // Whatever follows an escape is part of the string, including a quote. A newline is not:
-          // the line-ending backslash belongs to multi-line strings only, and letting one through
-          // would put a newline inside a value the formatter prints on a single line.
-          match self.bump() {
-            Some('\n') | Some('\r') | None => return self.error("unterminated string", Span::new(start, self.pos)),
-            Some(_) => {}
+          // A newline can't be escaped: the line-ending backslash belongs to multi-line strings
+          // only, and letting one through would put a newline inside a value the formatter prints
+          // on a single line.
+          if matches!(self.rest()[1..].chars().next(), Some('\n') | Some('\r') | None) {
+            self.bump();
+            return self.error("unterminated string", Span::new(start, self.pos));
           }
+          self.scan_escape()?;
         }
         Some('"') => {
           self.bump();
@@ -398,6 +590,51 @@ impl<'a> Parser<'a> {
     }
   }
 
+  /// Scans the escape sequence starting at the backslash under the cursor.
+  ///
+  /// A `\u` or `\U` escape has to name a Unicode scalar value, so a surrogate, or anything past the
+  /// last code point, is refused along with an escape TOML doesn't have.
+  fn scan_escape(&mut self) -> Result<(), SyntaxError> {
+    let start = self.pos;
+    self.bump(); // '\'
+    let (letter, digit_count) = match self.bump() {
+      Some('b' | 't' | 'n' | 'f' | 'r' | '"' | '\\') => return Ok(()),
+      // TOML 1.1's additions
+      Some(c @ ('e' | 'x')) if self.version == TomlVersion::V1_0 => {
+        return self.error(format!("the `\\{c}` escape requires TOML 1.1"), Span::new(start, self.pos));
+      }
+      Some('e') => return Ok(()),
+      Some('x') => ('x', 2),
+      Some('u') => ('u', 4),
+      Some('U') => ('U', 8),
+      Some(c) => return self.error(format!("invalid escape sequence `\\{c}`"), Span::new(start, self.pos)),
+      None => return self.error("unterminated string", Span::new(start, self.pos)),
+    };
+    let digits_start = self.pos;
+    for _ in 0..digit_count {
+      match self.peek() {
+        Some(c) if c.is_ascii_hexdigit() => {
+          self.bump();
+        }
+        _ => {
+          return self.error(
+            format!("expected {digit_count} hexadecimal digits in a `\\{letter}` escape"),
+            Span::new(start, self.pos),
+          )
+        }
+      }
+    }
+    let escape = &self.text[start..self.pos];
+    let code_point = u32::from_str_radix(&self.text[digits_start..self.pos], 16).expect("hexadecimal digits");
+    if (0xD800..=0xDFFF).contains(&code_point) {
+      return self.error(format!("`{escape}` is a surrogate, which a string cannot hold"), Span::new(start, self.pos));
+    }
+    if char::from_u32(code_point).is_none() {
+      return self.error(format!("`{escape}` is beyond the last Unicode code point"), Span::new(start, self.pos));
+    }
+    Ok(())
+  }
+
   /// Scans a `'` delimited string, single or multi-line, leaving the position after it.
   fn scan_literal_string(&mut self) -> Result<(), SyntaxError> {
     let start = self.pos;
@@ -432,9 +669,16 @@ impl<'a> Parser<'a> {
       match self.peek() {
         None => return self.error("unterminated multi-line string", Span::new(start, self.pos)),
         Some('\\') if has_escapes => {
-          self.bump();
-          if self.bump().is_none() {
+          // a backslash at the end of a line, give or take trailing whitespace, joins it to the next
+          let after = self.rest()[1..].trim_start_matches([' ', '\t']);
+          if after.is_empty() {
+            self.pos = self.text.len();
             return self.error("unterminated multi-line string", Span::new(start, self.pos));
+          } else if after.starts_with('\n') || after.starts_with("\r\n") {
+            self.bump();
+          } else if let Err(error) = self.scan_escape() {
+            self.string_open_at_error = Some(quote);
+            return Err(error);
           }
         }
         Some(c) if c == quote => {
@@ -539,11 +783,13 @@ impl<'a> Parser<'a> {
         }
       }
 
+      let ignored = !blank_line_before && pending_comments.last().is_some_and(|comment| comment.is_directive("dprint-ignore"));
       values.push(ArrayValue {
         value,
         leading_comments: std::mem::take(&mut pending_comments),
         trailing_comment,
         blank_line_before,
+        ignored,
       });
     }
 
@@ -563,19 +809,24 @@ impl<'a> Parser<'a> {
 
     // Only what directly follows the bracket decides this, so a collection whose first item sits
     // on the opening line is not considered multi-line however its later items were laid out.
-    let comment_after_open = self.parse_trailing_comment();
+    let comment_after_open = self.parse_inline_table_comment()?;
     let multi_line_in_source = comment_after_open.is_some() || matches!(self.peek(), Some('\n') | Some('\r'));
 
     let mut entries: Vec<Entry<'a>> = Vec::new();
     let mut pending_comments: Vec<Comment<'a>> = Vec::new();
     let mut newlines = 0usize;
     let mut separated = true;
+    // where the comma after the latest entry sits, which may turn out to be a trailing one
+    let mut last_comma = None;
 
     loop {
       self.skip_spaces();
       match self.peek() {
         None => return self.error("unterminated inline table", Span::new(start, self.pos)),
         Some('\n') | Some('\r') => {
+          if self.version == TomlVersion::V1_0 {
+            return self.error_here("a newline within an inline table requires TOML 1.1");
+          }
           // TOML 1.1 permits newlines within an inline table. A lone carriage return isn't one,
           // and leaving it would spin here forever.
           if !self.try_skip_newline() {
@@ -585,6 +836,7 @@ impl<'a> Parser<'a> {
           continue;
         }
         Some('#') => {
+          // only a comment on a line of its own reaches here, after a newline TOML 1.0 would refuse
           let has_preceding = !entries.is_empty() || !pending_comments.is_empty() || comment_after_open.is_some();
           let comment = self.parse_comment(has_preceding && newlines >= 2, 0);
           pending_comments.push(comment);
@@ -592,10 +844,11 @@ impl<'a> Parser<'a> {
           continue;
         }
         Some(',') if !separated => {
+          last_comma = Some(self.pos);
           self.bump();
           separated = true;
           newlines = 0;
-          if let Some(comment) = self.parse_trailing_comment() {
+          if let Some(comment) = self.parse_inline_table_comment()? {
             match entries.last_mut() {
               Some(entry) if entry.trailing_comment.is_none() => entry.trailing_comment = Some(comment),
               _ => pending_comments.push(comment),
@@ -604,6 +857,9 @@ impl<'a> Parser<'a> {
           continue;
         }
         Some('}') => {
+          if let (TomlVersion::V1_0, Some(comma), true) = (self.version, last_comma, separated) {
+            return self.error("a trailing comma in an inline table requires TOML 1.1", Span::new(comma, comma + 1));
+          }
           self.bump();
           break;
         }
@@ -620,13 +876,14 @@ impl<'a> Parser<'a> {
       // no interest
       let mut entry = self.parse_entry(blank_line_before, std::mem::take(&mut pending_comments), 0)?;
 
-      let mut trailing_comment = self.parse_trailing_comment();
+      let mut trailing_comment = self.parse_inline_table_comment()?;
       self.skip_spaces();
       separated = self.peek() == Some(',');
       if separated {
+        last_comma = Some(self.pos);
         self.bump();
         if trailing_comment.is_none() {
-          trailing_comment = self.parse_trailing_comment();
+          trailing_comment = self.parse_inline_table_comment()?;
         }
       }
       entry.trailing_comment = trailing_comment;
@@ -642,22 +899,253 @@ impl<'a> Parser<'a> {
   }
 }
 
-/// Whether the text could be an unquoted TOML value.
-///
-/// This is a shape check rather than a full parse: the formatter reproduces values verbatim, so it
-/// only needs to know that a token isn't something like `foo = BAR`. Every valid unquoted value is
-/// a keyword or begins with a digit or a sign, so nothing valid is turned away here. Whether the
-/// digits themselves form a real number or date-time is left to whatever reads the TOML.
-fn is_bare_value_shaped(text: &str) -> bool {
-  if matches!(text, "true" | "false" | "inf" | "+inf" | "-inf" | "nan" | "+nan" | "-nan") {
-    return true;
-  }
-  let starts_ok = matches!(text.chars().next(), Some(c) if c.is_ascii_digit() || c == '+' || c == '-');
-  // the space is only ever reached through a date-time's date/time separator
-  starts_ok
-    && text
-      .chars()
-      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '.' | '-' | '+' | ' '))
+/// Whether an unquoted value is a date-time rather than a number or boolean. A date-time begins
+/// with a `YYYY-` year or an `HH:` hour, neither of which a number can.
+pub(crate) fn is_date_time_shaped(text: &str) -> bool {
+  let bytes = text.as_bytes();
+  (bytes.len() >= 5 && bytes[0..4].iter().all(u8::is_ascii_digit) && bytes[4] == b'-')
+    || (bytes.len() >= 3 && bytes[0..2].iter().all(u8::is_ascii_digit) && bytes[2] == b':')
+}
+
+// ---- literals ----
+
+/// What is wrong with a literal, along with the byte range within its text that is at fault.
+struct LiteralError {
+  message: String,
+  range: Range<usize>,
+}
+
+impl LiteralError {
+  fn new(message: impl Into<String>, range: Range<usize>) -> Self {
+    LiteralError {
+      message: message.into(),
+      range,
+    }
+  }
+
+  /// An error pointing at the character of `text` that starts at `index`.
+  fn at_char(message: impl Into<String>, text: &str, index: usize) -> Self {
+    let len = text[index..].chars().next().map(char::len_utf8).unwrap_or(0);
+    LiteralError::new(message, index..index + len)
+  }
+}
+
+/// Checks an unquoted value that isn't a date-time against TOML's grammar for an integer, float
+/// or boolean.
+fn check_number_or_boolean(text: &str) -> Result<(), LiteralError> {
+  if matches!(text, "true" | "false") {
+    return Ok(());
+  }
+  let sign_len = usize::from(text.starts_with(['+', '-']));
+  let unsigned = &text[sign_len..];
+  if matches!(unsigned, "inf" | "nan") {
+    return Ok(());
+  }
+  // every number begins with a digit once its sign is set aside, so anything else isn't a value
+  if !unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+    return Err(LiteralError::new("expected a value", 0..text.len()));
+  }
+
+  let prefix = unsigned.get(..2).unwrap_or_default();
+  let radix = match prefix.to_ascii_lowercase().as_str() {
+    "0x" => Some((16, "a hexadecimal")),
+    "0o" => Some((8, "an octal")),
+    "0b" => Some((2, "a binary")),
+    _ => None,
+  };
+  if let Some((radix, name)) = radix {
+    let prefix_range = sign_len..sign_len + 2;
+    if sign_len > 0 {
+      return Err(LiteralError::new(format!("{name} number cannot have a sign"), 0..sign_len));
+    }
+    if prefix != prefix.to_ascii_lowercase() {
+      return Err(LiteralError::new(
+        format!("the `{}` prefix must be lowercase", prefix.to_ascii_lowercase()),
+        prefix_range,
+      ));
+    }
+    let end = scan_digits(text, 2, radix)?;
+    return match text[end..].chars().next() {
+      Some(c) if c.is_ascii_alphanumeric() => Err(LiteralError::at_char(format!("{c:?} is not {name} digit"), text, end)),
+      Some(c) => Err(LiteralError::at_char(format!("unexpected {c:?} in a number"), text, end)),
+      None if end == 2 => Err(LiteralError::new(format!("expected {name} digit after `{prefix}`"), prefix_range)),
+      None => Ok(()),
+    };
+  }
+
+  // the integer part of a decimal number, which is all of an integer
+  let mut pos = scan_digits(text, sign_len, 10)?;
+  if unsigned.starts_with('0') && pos > sign_len + 1 {
+    return Err(LiteralError::new("a decimal number cannot have leading zeros", sign_len..sign_len + 1));
+  }
+  if text[pos..].starts_with('.') {
+    let fraction_end = scan_digits(text, pos + 1, 10)?;
+    if fraction_end == pos + 1 {
+      return Err(LiteralError::new("expected a digit after the decimal point", pos..pos + 1));
+    }
+    pos = fraction_end;
+  }
+  if text[pos..].starts_with(['e', 'E']) {
+    let exponent_start = pos;
+    pos += 1;
+    if text[pos..].starts_with(['+', '-']) {
+      pos += 1;
+    }
+    // unlike the integer part, an exponent may have leading zeros
+    let exponent_end = scan_digits(text, pos, 10)?;
+    if exponent_end == pos {
+      return Err(LiteralError::new("expected a digit in the exponent", exponent_start..pos));
+    }
+    pos = exponent_end;
+  }
+  match text[pos..].chars().next() {
+    Some(c) => Err(LiteralError::at_char(format!("unexpected {c:?} in a number"), text, pos)),
+    None => Ok(()),
+  }
+}
+
+/// Scans the run of digits in the given radix, and the underscores between them, that starts at
+/// `start`, returning where it ends. An underscore has to sit between two digits.
+fn scan_digits(text: &str, start: usize, radix: u32) -> Result<usize, LiteralError> {
+  let is_digit = |index: usize| text.as_bytes().get(index).is_some_and(|b| (*b as char).is_digit(radix));
+  let mut pos = start;
+  loop {
+    match text.as_bytes().get(pos) {
+      Some(b'_') => {
+        if pos == start || !is_digit(pos - 1) || !is_digit(pos + 1) {
+          return Err(LiteralError::new("an underscore in a number must sit between two digits", pos..pos + 1));
+        }
+      }
+      // a byte of a multi-byte character is never a digit, so the run can't end partway into one
+      Some(_) if is_digit(pos) => {}
+      _ => return Ok(pos),
+    }
+    pos += 1;
+  }
+}
+
+/// Checks an unquoted value that begins like a date-time against TOML's grammar for an offset
+/// date-time, local date-time, local date or local time, down to whether the date is in the
+/// calendar. Seconds may be left off a time when reading TOML 1.1.
+fn check_date_time(text: &str, version: TomlVersion) -> Result<(), LiteralError> {
+  let is_local_time = text.as_bytes().get(2) == Some(&b':');
+  let time_start = if is_local_time {
+    0
+  } else {
+    check_date(text)?;
+    match text.as_bytes().get(10) {
+      None => return Ok(()),
+      Some(b'T' | b't' | b' ') => 11,
+      Some(_) => return Err(LiteralError::at_char("expected 'T' or a space between the date and the time", text, 10)),
+    }
+  };
+
+  let time_end = check_time(text, time_start, version)?;
+  let end = match text.as_bytes().get(time_end) {
+    // only a time with a date can have an offset
+    Some(b'Z' | b'z') if !is_local_time => time_end + 1,
+    Some(b'+' | b'-') if !is_local_time => {
+      let hour = two_digits(text, time_end + 1, "offset hour")?;
+      if hour > 23 {
+        return Err(LiteralError::new("the offset's hour must be between 00 and 23", time_end + 1..time_end + 3));
+      }
+      expect_char(text, time_end + 3, ':', "expected ':' after the offset's hour")?;
+      let minute = two_digits(text, time_end + 4, "offset minute")?;
+      if minute > 59 {
+        return Err(LiteralError::new("the offset's minute must be between 00 and 59", time_end + 4..time_end + 6));
+      }
+      time_end + 6
+    }
+    _ => time_end,
+  };
+  match text[end..].chars().next() {
+    Some(c) => Err(LiteralError::at_char(format!("unexpected {c:?} in a date-time"), text, end)),
+    None => Ok(()),
+  }
+}
+
+/// Checks the `YYYY-MM-DD` date a date-time starts with.
+fn check_date(text: &str) -> Result<(), LiteralError> {
+  // the year and the hyphen after it are what marked the value out as a date-time
+  let year = text[0..4].parse::<u32>().expect("four digits");
+  let month = two_digits(text, 5, "month")?;
+  if !(1..=12).contains(&month) {
+    return Err(LiteralError::new("the month must be between 01 and 12", 5..7));
+  }
+  expect_char(text, 7, '-', "expected '-' after the month")?;
+  let day = two_digits(text, 8, "day")?;
+  let days_in_month = match month {
+    2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  };
+  if !(1..=days_in_month).contains(&day) {
+    return Err(LiteralError::new(
+      format!("the day must be between 01 and {days_in_month} for {year:04}-{month:02}"),
+      8..10,
+    ));
+  }
+  Ok(())
+}
+
+/// Checks the `HH:MM[:SS[.fraction]]` time starting at `start`, returning where it ends.
+fn check_time(text: &str, start: usize, version: TomlVersion) -> Result<usize, LiteralError> {
+  let hour = two_digits(text, start, "hour")?;
+  if hour > 23 {
+    return Err(LiteralError::new("the hour must be between 00 and 23", start..start + 2));
+  }
+  expect_char(text, start + 2, ':', "expected ':' after the hour")?;
+  let minute = two_digits(text, start + 3, "minute")?;
+  if minute > 59 {
+    return Err(LiteralError::new("the minute must be between 00 and 59", start + 3..start + 5));
+  }
+  let mut pos = start + 5;
+  match text.as_bytes().get(pos) {
+    Some(b':') => {
+      let second = two_digits(text, pos + 1, "second")?;
+      // 60 is a leap second
+      if second > 60 {
+        return Err(LiteralError::new("the second must be between 00 and 60", pos + 1..pos + 3));
+      }
+      pos += 3;
+      if text.as_bytes().get(pos) == Some(&b'.') {
+        let digits = text[pos + 1..].bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+          return Err(LiteralError::new("expected a digit after the decimal point", pos..pos + 1));
+        }
+        pos += 1 + digits;
+      }
+    }
+    Some(b'.') => return Err(LiteralError::new("a time without seconds cannot have a fraction", pos..pos + 1)),
+    _ if version == TomlVersion::V1_0 => return Err(LiteralError::new("a time without seconds requires TOML 1.1", start..pos)),
+    _ => {}
+  }
+  Ok(pos)
+}
+
+/// The two digit number at `index`, which is the part of a date-time named by `what`.
+fn two_digits(text: &str, index: usize, what: &str) -> Result<u32, LiteralError> {
+  match text.get(index..index + 2) {
+    Some(digits) if digits.bytes().all(|b| b.is_ascii_digit()) => Ok(digits.parse().expect("two digits")),
+    _ => {
+      // point at what is there instead, or at the end of the value when nothing is
+      let end = text[index.min(text.len())..]
+        .char_indices()
+        .nth(2)
+        .map(|(i, _)| index + i)
+        .unwrap_or(text.len());
+      Err(LiteralError::new(format!("expected a two digit {what}"), index.min(text.len())..end))
+    }
+  }
+}
+
+fn expect_char(text: &str, index: usize, expected: char, message: &str) -> Result<(), LiteralError> {
+  if text[index.min(text.len())..].starts_with(expected) {
+    Ok(())
+  } else {
+    Err(LiteralError::at_char(message, text, index.min(text.len())))
+  }
 }
 
 /// Whether the text is exactly a `YYYY-MM-DD` date.
@@ -3,57 +3,362 @@
 // Everything here treats a blank line as a divider the author put there deliberately: each run
 // between blank lines is sorted on its own and runs never move past each other. Comments written
 // above something travel with it, except for the comments heading a run, which stay at its top.
+// Something a `dprint-ignore` directive covers divides its siblings the same way, staying exactly
+// where it is while those on either side of it are sorted separately.
 
 use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter::Peekable;
 
 use crate::ast::*;
 use crate::configuration::Configuration;
+use crate::configuration::SortOrder;
+use crate::configuration::UnlistedKeys;
+use crate::strings::string_value;
 
-/// Applies whichever of the sorting options are turned on.
+/// Applies whichever of the sorting options are turned on, along with `dedupeArrays`, which works
+/// through the same values.
 pub fn apply_sorting(root: &mut Root, config: &Configuration) {
-  if config.sort_keys {
-    sort_root_keys(root);
+  if config.sort_keys || !config.key_order.is_empty() {
+    sort_root_keys(root, config);
   }
-  if config.sort_arrays || config.sort_inline_tables {
+  if config.sort_tables {
+    sort_tables(root, config.sort_order);
+  }
+  if !config.sort_arrays_by.is_empty() {
+    sort_arrays_of_tables(root, config);
+  }
+  let within_values = config.sort_arrays || config.sort_inline_tables || config.dedupe_arrays;
+  if within_values || !config.key_order.is_empty() || !config.sort_arrays_by.is_empty() {
+    let mut path = String::new();
     for item in &mut root.items {
-      if let RootItem::Entry(entry) = item {
-        sort_within_value(&mut entry.value, config, LineContext::default());
+      match item {
+        RootItem::TableHeader(header) => path = table_path(&header.key),
+        RootItem::Entry(entry) if !entry.ignored => sort_within_value(&mut entry.value, &entry_path(&path, &entry.key), config, LineContext::default()),
+        RootItem::Entry(_) | RootItem::Comment(_) => {}
       }
     }
   }
 }
 
-/// Sorts the entries of every table in the file, including the one above the first table header.
+/// Sorts the entries of every table in the file, including the one above the first table header,
+/// in the order `keyOrder` gives for the table or otherwise alphabetically when `sortKeys` is on.
 ///
 /// Table headers themselves are left where they are: moving one would change which entries belong
 /// to it.
-fn sort_root_keys(root: &mut Root) {
+fn sort_root_keys(root: &mut Root, config: &Configuration) {
   let mut start = 0;
+  let mut path = String::new();
   while start <= root.items.len() {
     let end = section_end(&root.items, start);
-    sort_root_entries(&mut root.items, start, end, &|left, right| compare_keys(&left.key, &right.key));
+    match key_order_for(config, &path) {
+      Some(keys) => sort_root_entries(&mut root.items, start, end, &|left, right| {
+        compare_in_key_order(keys, config.key_order_unlisted, config.sort_order, left, right)
+      }),
+      None if config.sort_keys => sort_root_entries(&mut root.items, start, end, &|left, right| {
+        compare_keys(&left.key, &right.key, config.sort_order)
+      }),
+      None => {}
+    }
+    if let Some(RootItem::TableHeader(header)) = root.items.get(end) {
+      path = table_path(&header.key);
+    }
     // the header that ended the section isn't part of the next one
     start = end + 1;
   }
 }
 
-fn sort_within_value(value: &mut Value, config: &Configuration, line: LineContext) {
+/// The keys `keyOrder` lists for the table at `path`, from the first rule whose pattern matches it.
+pub fn key_order_for<'c>(config: &'c Configuration, path: &str) -> Option<&'c [String]> {
+  config
+    .key_order
+    .iter()
+    .find(|rule| matches_pattern(&rule.table, path))
+    .map(|rule| rule.keys.as_slice())
+}
+
+/// The key `sortArraysBy` gives for the array at `path`, from the first rule whose pattern matches
+/// it.
+fn sort_arrays_by_for<'c>(config: &'c Configuration, path: &str) -> Option<&'c str> {
+  config
+    .sort_arrays_by
+    .iter()
+    .find(|rule| matches_pattern(&rule.array, path))
+    .map(|rule| rule.key.as_str())
+}
+
+/// Compares two elements of an array by the values their entries for `key` hold. An element that
+/// isn't an inline table, or has no such entry or one whose value has no text, goes after all the
+/// rest, and those keep the order they were in.
+fn compare_by_entry(left: &Value, right: &Value, key: &str, order: SortOrder) -> Ordering {
+  compare_present_first(entry_sort_key(left, key), entry_sort_key(right, key), order)
+}
+
+/// The text the entry for `key` in an inline table sorts under, when it has one.
+fn entry_sort_key<'a>(value: &'a Value<'_>, key: &str) -> Option<&'a str> {
+  let ValueKind::InlineTable(table) = &value.kind else {
+    return None;
+  };
+  let entry = table
+    .entries
+    .iter()
+    .find(|entry| entry.key.rest.is_empty() && entry.key.first.unquoted_text() == key)?;
+  value_sort_key(&entry.value)
+}
+
+/// Compares two entries by where the first segment of each key sits in `keys`. A key that isn't
+/// listed sits where a `*` in the list does, or after every listed key when there is none, and is
+/// sorted among the others there or kept where it was, as `unlisted` says.
+pub fn compare_in_key_order(keys: &[impl AsRef<str>], unlisted: UnlistedKeys, order: SortOrder, left: &Entry, right: &Entry) -> Ordering {
+  let rank = |entry: &Entry| {
+    let name = entry.key.first.unquoted_text();
+    match keys.iter().position(|key| key.as_ref() == name) {
+      Some(position) => (position, true),
+      None => (keys.iter().position(|key| key.as_ref() == "*").unwrap_or(keys.len()), false),
+    }
+  };
+  match (rank(left), rank(right)) {
+    ((left_rank, false), (right_rank, false)) if left_rank == right_rank => match unlisted {
+      UnlistedKeys::Sort => compare_text(left.key.first.unquoted_text(), right.key.first.unquoted_text(), order),
+      UnlistedKeys::Maintain => Ordering::Equal,
+    },
+    // `serde` and `serde.workspace` share a rank, so they keep their order
+    ((left_rank, _), (right_rank, _)) => left_rank.cmp(&right_rank),
+  }
+}
+
+/// Whether the dotted table path `path` matches `pattern`, in which `*` stands for any run of
+/// characters, dots included.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+  match pattern.split_once('*') {
+    None => pattern == path,
+    Some((prefix, rest)) => {
+      let Some(path) = path.strip_prefix(prefix) else {
+        return false;
+      };
+      // the star takes as little as it can, and more each time what follows fails to match
+      path
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(path.len()))
+        .any(|i| matches_pattern(rest, &path[i..]))
+    }
+  }
+}
+
+/// The dotted path naming a table, with the quotes around any quoted segment taken off.
+pub fn table_path(key: &Key) -> String {
+  key.parts().map(|part| part.unquoted_text()).collect::<Vec<_>>().join(".")
+}
+
+/// The path of the table an entry's value is, when it is one, within the table at `path`.
+fn entry_path(path: &str, key: &Key) -> String {
+  match path {
+    "" => table_path(key),
+    path => format!("{path}.{}", table_path(key)),
+  }
+}
+
+/// Sorts the sections of the file by their headers' keys, each carrying its entries and the
+/// comments written above its header.
+///
+/// A section stays beneath the nearest one above it naming one of its ancestors, sorted among that
+/// one's other subtables, so `[a.b]` always follows `[a]` -- and follows the element of an array of
+/// tables it belongs to. The elements of an array of tables compare equal and the sort is stable,
+/// so they are never reordered among themselves. Whatever comes before the first header stays
+/// at the top. Unlike entries, sections aren't divided into runs by blank lines, since nearly every
+/// section is set apart from the one before it by one.
+fn sort_tables(root: &mut Root, order: SortOrder) {
+  let preamble_end = section_content_end(&root.items, 0);
+  let rest = root.items.split_off(preamble_end);
+  let header_indices = rest
+    .iter()
+    .enumerate()
+    .filter(|(_, item)| item.is_table_header())
+    .map(|(i, _)| i)
+    .collect::<Vec<_>>();
+  let mut starts = header_indices.iter().map(|&i| attached_comments_start(&rest, i)).collect::<Vec<_>>();
+  if let Some(first) = starts.first_mut() {
+    // anything left between the preamble and the first header is written against that header
+    *first = 0;
+  }
+
+  // each section's parent is the nearest section above it naming one of its ancestors, however many
+  // unrelated sections come between them -- a table within an array of tables belongs to the
+  // element above it, and sorting it anywhere else would move it into another
+  let keys = header_indices
+    .iter()
+    .map(|&i| match &rest[i] {
+      RootItem::TableHeader(header) => &header.key,
+      _ => unreachable!(),
+    })
+    .collect::<Vec<_>>();
+  let mut children: Vec<Vec<usize>> = vec![Vec::new(); keys.len() + 1];
+  let mut parents = Vec::with_capacity(keys.len());
+  // the latest section to name each table so far
+  let mut latest: HashMap<Vec<&str>, usize> = HashMap::new();
+  for (i, key) in keys.iter().enumerate() {
+    let names = key.parts().map(KeyPart::unquoted_text).collect::<Vec<_>>();
+    let parent = (1..names.len()).filter_map(|len| latest.get(&names[..len]).copied()).max();
+    // the last slot holds the sections with no parent
+    children[parent.unwrap_or(keys.len())].push(i);
+    parents.push(parent);
+    latest.insert(names, i);
+  }
+
+  // a section holding something that stays where it is stays put itself, and so do its ancestors,
+  // which would otherwise carry it off along with them
+  let mut fixed = (0..starts.len())
+    .map(|i| rest[starts[i]..starts.get(i + 1).copied().unwrap_or(rest.len())].iter().any(is_fixed))
+    .collect::<Vec<_>>();
+  for i in (0..keys.len()).rev() {
+    if let (true, Some(parent)) = (fixed[i], parents[i]) {
+      fixed[parent] = true;
+    }
+  }
+  for siblings in &mut children {
+    for run in siblings.split_mut(|&i| fixed[i]) {
+      run.sort_by(|&left, &right| compare_keys(keys[left], keys[right], order));
+    }
+  }
+  let mut order = Vec::with_capacity(keys.len());
+  let mut pending = children[keys.len()].iter().rev().copied().collect::<Vec<_>>();
+  while let Some(i) = pending.pop() {
+    order.push(i);
+    pending.extend(children[i].iter().rev());
+  }
+
+  let mut sections = Vec::with_capacity(starts.len());
+  let mut rest = rest;
+  for &start in starts.iter().rev() {
+    sections.push(Some(rest.split_off(start)));
+  }
+  sections.reverse();
+  // a file starting with a header has no blank line above it, which the section that now starts it
+  // takes on, and the section that did gains one like any other
+  if preamble_end == 0 && order.first().is_some_and(|&first| first != 0) {
+    set_blank_line_before(&mut sections[order[0]], false);
+    set_blank_line_before(&mut sections[0], true);
+  }
+  for i in order {
+    root.items.extend(sections[i].take().expect("each section is written once"));
+  }
+}
+
+fn set_blank_line_before(section: &mut Option<Vec<RootItem>>, value: bool) {
+  if let Some(item) = section.as_mut().and_then(|items| items.first_mut()) {
+    item.set_blank_line_before(value);
+  }
+}
+
+/// Sorts each run of `[[table]]` sections naming an array that `sortArraysBy` gives a key for, by
+/// the value each element's entry for that key holds. An element carries the comments above its
+/// header and the sections of the tables within it, and those missing the key go after the rest in
+/// the order they were in. Sections of the array that some other section separates from the run
+/// are sorted on their own, since moving one past it would say something different.
+fn sort_arrays_of_tables(root: &mut Root, config: &Configuration) {
+  let mut index = 0;
+  while let Some(offset) = root.items[index..].iter().position(RootItem::is_table_header) {
+    index += offset;
+    let sort_by = match &root.items[index] {
+      RootItem::TableHeader(header) if header.is_array_of_tables => sort_arrays_by_for(config, &table_path(&header.key)),
+      _ => None,
+    };
+    let Some(sort_by) = sort_by else {
+      index += 1;
+      continue;
+    };
+
+    let bounds = array_of_tables_elements(&root.items, index);
+    let (start, end) = (bounds[0], bounds[bounds.len() - 1]);
+    let mut run = root.items.drain(start..end).collect::<Vec<_>>();
+    let mut elements = Vec::with_capacity(bounds.len() - 1);
+    for &bound in bounds[1..bounds.len() - 1].iter().rev() {
+      elements.push(run.split_off(bound - start));
+    }
+    elements.push(run);
+    elements.reverse();
+
+    // each place in the run keeps the blank line it had above it, whichever element now fills it
+    let blank_lines = elements.iter().map(|element| element[0].blank_line_before()).collect::<Vec<_>>();
+    for run in elements.split_mut(|element| element.iter().any(is_fixed)) {
+      run.sort_by(|left, right| compare_present_first(element_sort_key(left, sort_by), element_sort_key(right, sort_by), config.sort_order));
+    }
+    for (element, blank_line) in elements.iter_mut().zip(blank_lines) {
+      element[0].set_blank_line_before(blank_line);
+    }
+    root.items.splice(start..start, elements.into_iter().flatten());
+    index = end;
+  }
+}
+
+/// Where each element of the run of `[[table]]` sections naming the same array as the header at
+/// `index` starts, followed by where the run ends. An element runs up to the next header that
+/// names neither a table within it nor the array's next element, less the comments written above
+/// that header.
+fn array_of_tables_elements(items: &[RootItem], index: usize) -> Vec<usize> {
+  let RootItem::TableHeader(header) = &items[index] else {
+    unreachable!();
+  };
+  let mut bounds = vec![attached_comments_start(items, index)];
+  let mut start = index + 1;
+  loop {
+    let end = section_end(items, start);
+    match items.get(end) {
+      Some(RootItem::TableHeader(next)) if header.key.is_strict_prefix_of(&next.key) => {}
+      Some(RootItem::TableHeader(next)) if next.is_array_of_tables && next.key.names_same_table(&header.key) => {
+        bounds.push(attached_comments_start(items, end));
+      }
+      _ => {
+        bounds.push(section_content_end(items, start));
+        return bounds;
+      }
+    }
+    start = end + 1;
+  }
+}
+
+/// The text the entry for `key` in an element of an array of tables sorts under, when it has one.
+fn element_sort_key<'a>(element: &'a [RootItem<'_>], key: &str) -> Option<&'a str> {
+  let header = element.iter().position(RootItem::is_table_header)?;
+  element[header + 1..]
+    .iter()
+    .take_while(|item| !item.is_table_header())
+    .find_map(|item| match item {
+      RootItem::Entry(entry) if entry.key.rest.is_empty() && entry.key.first.unquoted_text() == key => value_sort_key(&entry.value),
+      _ => None,
+    })
+}
+
+/// Sorts within a value that sits at `path`, which only matters when it is an inline table.
+fn sort_within_value(value: &mut Value, path: &str, config: &Configuration, line: LineContext) {
   match &mut value.kind {
     ValueKind::Array(array) => {
       // an array collapsed onto its table's line keeps nothing that could divide it into runs
       let single_line = line.arrays_collapsed || !array.force_use_new_lines(config);
-      for item in &mut array.values {
-        // a table reached through an array is still kept on the enclosing table's line
-        sort_within_value(&mut item.value, config, line);
+      // a table reached through an array is still kept on the enclosing table's line, and
+      // counts as the array's table for `keyOrder`, as an element of an array of tables would
+      for item in array.values.iter_mut().filter(|item| !item.ignored) {
+        sort_within_value(&mut item.value, path, config, line);
+      }
+      if config.dedupe_arrays {
+        dedupe_with_comments(&mut array.values, |item| held_value(&item.value));
       }
-      // Only the text of a value decides where it sorts, so an array holding one that has no text
-      // of its own — another array, or an inline table — is left alone rather than being
-      // shuffled around an ordering that says nothing.
-      if config.sort_arrays && array.values.iter().all(|item| value_sort_key(&item.value).is_some()) {
+      if let Some(key) = sort_arrays_by_for(config, path) {
+        if single_line {
+          forget_blank_lines(&mut array.values);
+        }
+        sort_with_comments(&mut array.values, |left, right| {
+          compare_by_entry(&left.value, &right.value, key, config.sort_order)
+        });
+      } else if config.sort_arrays && array.values.iter().all(|item| value_sort_key(&item.value).is_some()) {
+        // only the text of a value decides where it sorts, so an array holding one that has no text
+        // of its own -- another array, or an inline table -- is left alone rather than being
+        // shuffled around an ordering that says nothing
         if single_line {
           forget_blank_lines(&mut array.values);
         }
-        sort_with_comments(&mut array.values, |left, right| value_sort_key(&left.value).cmp(&value_sort_key(&right.value)));
+        sort_with_comments(&mut array.values, |left, right| compare_values(&left.value, &right.value, config.sort_order));
       }
     }
     ValueKind::InlineTable(table) => {
@@ -62,14 +367,24 @@ fn sort_within_value(value: &mut Value, config: &Configuration, line: LineContex
         within_single_line_table: single_line,
         arrays_collapsed: line.arrays_collapsed || (single_line && table.contains_multi_line_string()),
       };
-      for entry in &mut table.entries {
-        sort_within_value(&mut entry.value, config, inner);
+      for entry in table.entries.iter_mut().filter(|entry| !entry.ignored) {
+        sort_within_value(&mut entry.value, &entry_path(path, &entry.key), config, inner);
       }
-      if config.sort_inline_tables {
+      if config.dedupe_arrays {
+        // only an entry repeating another's value as well as its key can go without losing anything
+        dedupe_with_comments(&mut table.entries, |entry| Some((table_path(&entry.key), held_value(&entry.value)?)));
+      }
+      let key_order = key_order_for(config, path);
+      if key_order.is_some() || config.sort_inline_tables {
         if single_line {
           forget_blank_lines(&mut table.entries);
         }
-        sort_with_comments(&mut table.entries, |left, right| compare_keys(&left.key, &right.key));
+        match key_order {
+          Some(keys) => sort_with_comments(&mut table.entries, |left, right| {
+            compare_in_key_order(keys, config.key_order_unlisted, config.sort_order, left, right)
+          }),
+          None => sort_with_comments(&mut table.entries, |left, right| compare_keys(&left.key, &right.key, config.sort_order)),
+        }
       }
     }
     ValueKind::Scalar(_) | ValueKind::MultiLineString(_) => {}
@@ -89,12 +404,12 @@ fn forget_blank_lines<'a>(values: &mut [impl Sortable<'a>]) {
 }
 
 /// Compares two keys segment by segment, ignoring quoting so that `"serde"` sorts beside `serde`.
-fn compare_keys(left: &Key, right: &Key) -> Ordering {
+fn compare_keys(left: &Key, right: &Key, order: SortOrder) -> Ordering {
   let mut left = left.parts();
   let mut right = right.parts();
   loop {
     match (left.next(), right.next()) {
-      (Some(left), Some(right)) => match left.unquoted_text().cmp(right.unquoted_text()) {
+      (Some(left), Some(right)) => match compare_text(left.unquoted_text(), right.unquoted_text(), order) {
         Ordering::Equal => continue,
         ordering => return ordering,
       },
@@ -109,7 +424,7 @@ fn compare_keys(left: &Key, right: &Key) -> Ordering {
 ///
 /// A string sorts by its contents, so that the quote it happens to be written with — which the
 /// `quoteStyle` option may go on to change anyway — doesn't decide where it lands.
-pub fn value_sort_key<'a>(value: &'a Value<'_>) -> Option<&'a str> {
+fn value_sort_key<'a>(value: &'a Value<'_>) -> Option<&'a str> {
   match &value.kind {
     ValueKind::Scalar(text) => Some(unquoted(text, &["\"", "'"])),
     ValueKind::MultiLineString(text) => Some(unquoted(text, &["\"\"\"", "'''"])),
@@ -117,6 +432,118 @@ pub fn value_sort_key<'a>(value: &'a Value<'_>) -> Option<&'a str> {
   }
 }
 
+/// What a value holds, as far as telling whether two values are the same goes. Strings are told
+/// apart by their contents and numbers by the number they spell, so that `"a"` repeats `'a'` and
+/// `1_000` repeats `1000`, while anything else has to be written the same way to repeat another.
+#[derive(PartialEq)]
+enum HeldValue {
+  String(String),
+  Integer(i128),
+  Float(u64),
+  Other(String),
+}
+
+/// What a value holds, or `None` for a collection or a multi-line string, which are never taken
+/// to repeat anything.
+fn held_value(value: &Value) -> Option<HeldValue> {
+  let ValueKind::Scalar(text) = &value.kind else {
+    return None;
+  };
+  if let Some(string) = string_value(text) {
+    return Some(HeldValue::String(string.into_owned()));
+  }
+  // the parser has already checked the number is well formed, so its shape is all that's looked at
+  let digits = text.replace('_', "");
+  let (negative, unsigned) = match digits.strip_prefix('-') {
+    Some(unsigned) => (true, unsigned),
+    None => (false, digits.strip_prefix('+').unwrap_or(&digits)),
+  };
+  let integer = match unsigned.get(..2) {
+    Some("0x") => i128::from_str_radix(&unsigned[2..], 16).ok(),
+    Some("0o") => i128::from_str_radix(&unsigned[2..], 8).ok(),
+    Some("0b") => i128::from_str_radix(&unsigned[2..], 2).ok(),
+    _ if unsigned.bytes().all(|b| b.is_ascii_digit()) => unsigned.parse().ok(),
+    _ => None,
+  };
+  if let Some(integer) = integer {
+    return Some(HeldValue::Integer(if negative { -integer } else { integer }));
+  }
+  match digits.parse::<f64>() {
+    Ok(float) => Some(HeldValue::Float(float.to_bits())),
+    Err(_) => Some(HeldValue::Other(text.to_string())),
+  }
+}
+
+/// Compares two values by the text they sort under, with collections, which have none, after all
+/// the rest.
+pub fn compare_values(left: &Value, right: &Value, order: SortOrder) -> Ordering {
+  compare_present_first(value_sort_key(left), value_sort_key(right), order)
+}
+
+/// Compares the text two things sort under, with those that have none after all the rest, where
+/// they keep the order they were in.
+fn compare_present_first(left: Option<&str>, right: Option<&str>, order: SortOrder) -> Ordering {
+  match (left, right) {
+    (Some(left), Some(right)) => compare_text(left, right, order),
+    (left, right) => left.is_none().cmp(&right.is_none()),
+  }
+}
+
+/// Compares two keys or values by their text, in the order the `sortOrder` option asks for. Text
+/// that only differs in what that order overlooks, such as case, is then compared character by
+/// character, so that it lands in the same place however it was written before.
+pub fn compare_text(left: &str, right: &str, order: SortOrder) -> Ordering {
+  let ordering = match order {
+    SortOrder::Ordinal => Ordering::Equal,
+    SortOrder::CaseInsensitive => left.chars().flat_map(char::to_lowercase).cmp(right.chars().flat_map(char::to_lowercase)),
+    SortOrder::Natural => compare_natural(left, right),
+  };
+  ordering.then_with(|| left.cmp(right))
+}
+
+/// Compares text with each run of digits standing for the number it spells, so that `item2` comes
+/// before `item10`, and letters compared without regard to case.
+///
+/// A digit only ever meets a letter or a symbol here when the text around it differs, and since
+/// the digits sit together among the characters, it makes no difference which digit it is.
+fn compare_natural(left: &str, right: &str) -> Ordering {
+  let mut left = left.chars().flat_map(char::to_lowercase).peekable();
+  let mut right = right.chars().flat_map(char::to_lowercase).peekable();
+  loop {
+    match (left.peek(), right.peek()) {
+      (None, None) => return Ordering::Equal,
+      (None, Some(_)) => return Ordering::Less,
+      (Some(_), None) => return Ordering::Greater,
+      (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+        let (l, r) = (take_number(&mut left), take_number(&mut right));
+        // with the leading zeros gone, a longer run of digits is a larger number
+        match l.len().cmp(&r.len()).then_with(|| l.cmp(&r)) {
+          Ordering::Equal => {}
+          ordering => return ordering,
+        }
+      }
+      (Some(l), Some(r)) => match l.cmp(r) {
+        Ordering::Equal => {
+          left.next();
+          right.next();
+        }
+        ordering => return ordering,
+      },
+    }
+  }
+}
+
+/// Takes the run of digits `chars` is at, without its leading zeros.
+fn take_number(chars: &mut Peekable<impl Iterator<Item = char>>) -> String {
+  let mut digits = String::new();
+  while let Some(c) = chars.next_if(char::is_ascii_digit) {
+    if c != '0' || !digits.is_empty() {
+      digits.push(c);
+    }
+  }
+  digits
+}
+
 fn unquoted<'a>(text: &'a str, quotes: &[&str]) -> &'a str {
   for quote in quotes {
     if let Some(inner) = text.strip_prefix(quote).and_then(|text| text.strip_suffix(quote)) {
@@ -139,6 +566,42 @@ pub fn section_end(items: &[RootItem], start: usize) -> usize {
   }
 }
 
+/// The index just past a section's own content, which leaves out any comments at its end that are
+/// written directly above the header that follows it, since those describe that header instead.
+pub fn section_content_end(items: &[RootItem], start: usize) -> usize {
+  let end = section_end(items, start);
+  if end < items.len() {
+    attached_comments_start(items, end).max(start)
+  } else {
+    end
+  }
+}
+
+/// The index of the first of the comments written directly above `items[index]`, with no blank line
+/// between them, or `index` itself when there are none. Such comments describe the item beneath
+/// them and go wherever it goes.
+pub fn attached_comments_start(items: &[RootItem], index: usize) -> usize {
+  let mut start = index;
+  while start > 0 && !items[start].blank_line_before() && matches!(items[start - 1], RootItem::Comment(_)) {
+    start -= 1;
+  }
+  start
+}
+
+/// Whether `item` has to stay where it is, because a `dprint-ignore` directive covers it or it opens
+/// or closes a range that does. A range's directives are kept with it so that it still covers the
+/// same items when the output is formatted again.
+pub fn is_fixed(item: &RootItem) -> bool {
+  match item {
+    RootItem::Comment(comment) => is_fixed_comment(comment),
+    item => item.is_ignored(),
+  }
+}
+
+fn is_fixed_comment(comment: &Comment) -> bool {
+  comment.ignored || comment.is_directive("dprint-ignore-start") || comment.is_directive("dprint-ignore-end")
+}
+
 /// Something that can be sorted among its siblings, carrying the comments written above it.
 pub trait Sortable<'a> {
   fn leading_comments(&self) -> &[Comment<'a>];
@@ -146,6 +609,9 @@ pub trait Sortable<'a> {
   fn set_leading_comments(&mut self, comments: Vec<Comment<'a>>);
   fn blank_line_before(&self) -> bool;
   fn set_blank_line_before(&mut self, value: bool);
+  fn trailing_comment_mut(&mut self) -> &mut Option<Comment<'a>>;
+  /// Whether a `dprint-ignore` directive covers this value, which keeps it where it is.
+  fn is_ignored(&self) -> bool;
 
   /// Clears the blank line above each of this value's leading comments, which count towards the
   /// run it starts just as its own does.
@@ -168,6 +634,12 @@ impl<'a> Sortable<'a> for ArrayValue<'a> {
   fn set_blank_line_before(&mut self, value: bool) {
     self.blank_line_before = value;
   }
+  fn trailing_comment_mut(&mut self) -> &mut Option<Comment<'a>> {
+    &mut self.trailing_comment
+  }
+  fn is_ignored(&self) -> bool {
+    self.ignored
+  }
   fn forget_comment_blank_lines(&mut self) {
     for comment in &mut self.leading_comments {
       comment.blank_line_before = false;
@@ -191,6 +663,12 @@ impl<'a> Sortable<'a> for Entry<'a> {
   fn set_blank_line_before(&mut self, value: bool) {
     self.blank_line_before = value;
   }
+  fn trailing_comment_mut(&mut self) -> &mut Option<Comment<'a>> {
+    &mut self.trailing_comment
+  }
+  fn is_ignored(&self) -> bool {
+    self.ignored
+  }
   fn forget_comment_blank_lines(&mut self) {
     for comment in &mut self.leading_comments {
       comment.blank_line_before = false;
@@ -211,6 +689,7 @@ pub fn sort_with_comments<'a, T: Sortable<'a>>(values: &mut Vec<T>, cmp: impl Fn
         .unwrap_or(value.blank_line_before()),
       inner_blank: !value.leading_comments().is_empty() && value.blank_line_before(),
       starts_group: value.blank_line_before() || value.leading_comments().iter().any(|c| c.blank_line_before),
+      fixed: value.is_ignored(),
       leading_comments: value.take_leading_comments(),
       value,
     })
@@ -229,6 +708,57 @@ pub fn sort_with_comments<'a, T: Sortable<'a>>(values: &mut Vec<T>, cmp: impl Fn
   }));
 }
 
+/// Drops each value that repeats an earlier one, going by what `value_key` says each holds, and
+/// moves the comments written around a dropped value onto the one it repeats. A value `value_key`
+/// gives nothing for is never dropped, and nor is one a `dprint-ignore` directive covers.
+pub fn dedupe_with_comments<'a, T: Sortable<'a>, K: PartialEq>(values: &mut Vec<T>, value_key: impl Fn(&T) -> Option<K>) {
+  let mut kept: Vec<(T, Option<K>)> = Vec::with_capacity(values.len());
+  // a dropped value that started a group leaves the blank line dividing it to the next value kept
+  let mut starts_group = false;
+  for mut value in values.drain(..) {
+    let key = if value.is_ignored() { None } else { value_key(&value) };
+    let survivor = match &key {
+      Some(key) => kept.iter_mut().find(|(_, other)| other.as_ref() == Some(key)),
+      None => None,
+    };
+    let Some((survivor, _)) = survivor else {
+      if std::mem::take(&mut starts_group) {
+        let mut comments = value.take_leading_comments();
+        match comments.first_mut() {
+          Some(first) => first.blank_line_before = true,
+          None => value.set_blank_line_before(true),
+        }
+        value.set_leading_comments(comments);
+      }
+      kept.push((value, key));
+      continue;
+    };
+
+    starts_group |= value.blank_line_before() || value.leading_comments().iter().any(|c| c.blank_line_before);
+    let mut comments = value.take_leading_comments();
+    // the comment beside the dropped value goes beside the one it repeats, if there's room there
+    match (survivor.trailing_comment_mut(), value.trailing_comment_mut().take()) {
+      (trailing @ None, comment) => *trailing = comment,
+      (Some(_), comment) => comments.extend(comment),
+    }
+    if comments.is_empty() {
+      continue;
+    }
+    for comment in &mut comments {
+      comment.blank_line_before = false;
+    }
+    let mut leading_comments = survivor.take_leading_comments();
+    // a blank line above a value with no comments moves above the first of those it gains
+    if leading_comments.is_empty() && survivor.blank_line_before() {
+      survivor.set_blank_line_before(false);
+      comments[0].blank_line_before = true;
+    }
+    leading_comments.append(&mut comments);
+    survivor.set_leading_comments(leading_comments);
+  }
+  values.extend(kept.into_iter().map(|(value, _)| value));
+}
+
 /// One sortable thing along with the comments written above it.
 struct Unit<'a, T> {
   leading_comments: Vec<Comment<'a>>,
@@ -241,10 +771,13 @@ struct Unit<'a, T> {
   /// author uses to divide a section into groups. It may sit either above this unit's comments or
   /// between them and the item itself.
   starts_group: bool,
+  /// Whether the unit stays where it is, dividing those either side of it into separate runs.
+  fixed: bool,
   value: T,
 }
 
-/// Sorts the entries of `items[start..end]`, keeping each entry's own comments with it.
+/// Sorts the entries of `items[start..end]`, keeping each entry's own comments with it. An entry
+/// stays where it is when it, or any of its comments, does.
 pub fn sort_root_entries(items: &mut Vec<RootItem>, start: usize, end: usize, cmp: &impl Fn(&Entry, &Entry) -> Ordering) {
   // Split the section into one unit per entry, each carrying the comments written above it. Any
   // comments after the final entry belong to no entry and stay where they are.
@@ -258,6 +791,7 @@ pub fn sort_root_entries(items: &mut Vec<RootItem>, start: usize, end: usize, cm
         leading_blank: pending.first().map(|c| c.blank_line_before).unwrap_or(entry.blank_line_before),
         inner_blank: !pending.is_empty() && entry.blank_line_before,
         starts_group: entry.blank_line_before || pending.iter().any(|c| c.blank_line_before),
+        fixed: entry.ignored || ties_in_place(&pending, &entry),
         leading_comments: std::mem::take(&mut pending),
         value: entry,
       }),
@@ -293,6 +827,18 @@ pub fn sort_root_entries(items: &mut Vec<RootItem>, start: usize, end: usize, cm
   items.splice(start..start, sorted);
 }
 
+/// Whether the comments above a root entry tie it to where it is: they take in something a
+/// `dprint-ignore-start` range covers, or one of the range's directives is written flush against the
+/// entry, which would take it along wherever the entry went.
+fn ties_in_place(comments: &[Comment], entry: &Entry) -> bool {
+  let flush_start = if entry.blank_line_before {
+    comments.len()
+  } else {
+    comments.iter().rposition(|c| c.blank_line_before).unwrap_or(0)
+  };
+  comments.iter().any(|c| c.ignored) || comments[flush_start..].iter().any(is_fixed_comment)
+}
+
 /// Sorts `units` in place, treating a blank line as a divider the author put there deliberately:
 /// each run between blank lines is sorted on its own and runs never move past each other.
 ///
@@ -353,7 +899,9 @@ fn sort_units<T>(units: &mut [Unit<T>], cmp: impl Fn(&T, &T) -> Ordering) {
       (Vec::new(), false)
     };
 
-    group.sort_by(|left, right| cmp(&left.value, &right.value));
+    for run in group.split_mut(|unit| unit.fixed) {
+      run.sort_by(|left, right| cmp(&left.value, &right.value));
+    }
 
     for unit in group.iter_mut() {
       unit.leading_blank = false;
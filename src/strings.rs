@@ -0,0 +1,188 @@
+// Reading and writing the value a single-line string holds.
+//
+// Everywhere else a string is kept as the author spelt it. These are for the few places that have
+// to look past the spelling -- to write a string with a different quote than the one it came with,
+// say -- where the value is decoded and then encoded again, so that it comes out unchanged.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::str::Chars;
+
+use crate::configuration::LetterCase;
+use crate::configuration::TomlVersion;
+
+/// The value of a single-line basic or literal string, or `None` for anything else.
+///
+/// The parser has already checked every escape, so a basic string is always decoded in full.
+pub fn string_value(text: &str) -> Option<Cow<'_, str>> {
+  if let Some(inner) = text.strip_prefix('\'').and_then(|text| text.strip_suffix('\'')) {
+    return Some(Cow::Borrowed(inner));
+  }
+  let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+  if !inner.contains('\\') {
+    return Some(Cow::Borrowed(inner));
+  }
+  let mut value = String::with_capacity(inner.len());
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      value.push(c);
+      continue;
+    }
+    value.push(read_escape(&mut chars)?.0);
+  }
+  Some(Cow::Owned(value))
+}
+
+/// A single-line basic string with every character written the one way it is best written, or
+/// `None` for any other string or one already written that way.
+///
+/// A printable character is written as itself, a character with a short escape is written with it,
+/// and anything else that is invisible -- a control character, or a space or mark that can't be
+/// seen -- is written with a `\u` escape, whose digits are in `hex_case`. `\e` is only a short
+/// escape in TOML 1.1, so for TOML 1.0 an escape character is written with `\u` as well.
+pub fn normalized_escapes(text: &str, hex_case: LetterCase, version: TomlVersion) -> Option<String> {
+  let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+  let mut result = String::with_capacity(text.len());
+  result.push('"');
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      push_normalized(&mut result, c, hex_case, version);
+      continue;
+    }
+    // the parser has already checked every escape, so reading one can't fail
+    let (c, lowercase) = read_escape(&mut chars)?;
+    let hex_case = match hex_case {
+      LetterCase::Maintain if lowercase => LetterCase::Lower,
+      hex_case => hex_case,
+    };
+    push_normalized(&mut result, c, hex_case, version);
+  }
+  result.push('"');
+  (result != text).then_some(result)
+}
+
+/// Writes `c` into a basic string the way [`normalized_escapes`] does.
+fn push_normalized(result: &mut String, c: char, hex_case: LetterCase, version: TomlVersion) {
+  match c {
+    '"' => result.push_str("\\\""),
+    '\\' => result.push_str("\\\\"),
+    '\u{8}' => result.push_str("\\b"),
+    '\t' => result.push_str("\\t"),
+    '\n' => result.push_str("\\n"),
+    '\u{c}' => result.push_str("\\f"),
+    '\r' => result.push_str("\\r"),
+    '\u{1b}' if version == TomlVersion::V1_1 => result.push_str("\\e"),
+    c if is_invisible(c) => {
+      let code_point = u32::from(c);
+      let (letter, width) = if code_point > 0xFFFF { ('U', 8) } else { ('u', 4) };
+      result.push('\\');
+      result.push(letter);
+      match hex_case {
+        LetterCase::Lower => write!(result, "{code_point:0width$x}"),
+        LetterCase::Upper | LetterCase::Maintain => write!(result, "{code_point:0width$X}"),
+      }
+      .unwrap();
+    }
+    c => result.push(c),
+  }
+}
+
+/// Reads the escape whose backslash `chars` has just passed, giving the character it stands for
+/// and whether any of its hexadecimal digits are lowercase.
+fn read_escape(chars: &mut Chars) -> Option<(char, bool)> {
+  let digits_len = match chars.next()? {
+    'x' => 2,
+    'u' => 4,
+    'U' => 8,
+    letter => return Some((short_escape_char(letter)?, false)),
+  };
+  let digits = chars.as_str().get(..digits_len)?;
+  *chars = chars.as_str()[digits_len..].chars();
+  let c = char::from_u32(u32::from_str_radix(digits, 16).ok()?)?;
+  Some((c, digits.chars().any(|digit| digit.is_ascii_lowercase())))
+}
+
+/// The character a short escape like `\n` stands for, given the letter after its backslash.
+fn short_escape_char(letter: char) -> Option<char> {
+  match letter {
+    'b' => Some('\u{8}'),
+    't' => Some('\t'),
+    'n' => Some('\n'),
+    'f' => Some('\u{c}'),
+    'r' => Some('\r'),
+    'e' => Some('\u{1b}'),
+    '"' => Some('"'),
+    '\\' => Some('\\'),
+    _ => None,
+  }
+}
+
+/// `value` written as a basic string, escaping only what has to be: the double quote, the
+/// backslash, and the control characters, of which a tab is the one a string may hold as it is.
+pub fn to_basic_string(value: &str) -> String {
+  let mut text = String::with_capacity(value.len() + 2);
+  text.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => text.push_str("\\\""),
+      '\\' => text.push_str("\\\\"),
+      '\u{8}' => text.push_str("\\b"),
+      '\n' => text.push_str("\\n"),
+      '\u{c}' => text.push_str("\\f"),
+      '\r' => text.push_str("\\r"),
+      // `\u` rather than TOML 1.1's `\e` or `\x`, which a TOML 1.0 reader would reject
+      c if is_control(c) => write!(text, "\\u{:04X}", u32::from(c)).unwrap(),
+      c => text.push(c),
+    }
+  }
+  text.push('"');
+  text
+}
+
+/// `value` written as a literal string, or `None` when a literal string can't hold it, since it has
+/// no escapes with which to write a single quote or a control character.
+pub fn to_literal_string(value: &str) -> Option<String> {
+  if value.chars().any(|c| c == '\'' || is_control(c)) {
+    return None;
+  }
+  Some(format!("'{value}'"))
+}
+
+/// Whether `text` holds a character that [`normalized_escapes`] writes as an escape, which a literal
+/// string has no way to do.
+pub fn has_invisible(text: &str) -> bool {
+  text.chars().any(is_invisible)
+}
+
+/// Whether a string has to escape `c`, which TOML asks of every control character but the tab.
+fn is_control(c: char) -> bool {
+  matches!(c, '\u{0}'..='\u{8}' | '\u{a}'..='\u{1f}' | '\u{7f}')
+}
+
+/// Whether `c` can't be seen when written as itself, or can't be told apart from a plain space: the
+/// control characters, the spaces other than U+0020, and the marks that only shape the text
+/// around them.
+fn is_invisible(c: char) -> bool {
+  matches!(
+    c,
+    '\u{0}'..='\u{1f}'
+      | '\u{7f}'..='\u{a0}'
+      | '\u{ad}'
+      | '\u{34f}'
+      | '\u{61c}'
+      | '\u{115f}'..='\u{1160}'
+      | '\u{1680}'
+      | '\u{17b4}'..='\u{17b5}'
+      | '\u{180e}'
+      | '\u{2000}'..='\u{200f}'
+      | '\u{2028}'..='\u{202f}'
+      | '\u{205f}'..='\u{206f}'
+      | '\u{3000}'
+      | '\u{3164}'
+      | '\u{feff}'
+      | '\u{ffa0}'
+      | '\u{fff0}'..='\u{fffb}'
+  )
+}
@@ -0,0 +1,548 @@
+// Converting between an entry whose value is an inline table and a `[table]` section, for the
+// downlevel pass and the `inlineTable` options.
+//
+// `dep = { version = "1" }` under `[t]` defines the same table as `version = "1"` under `[t.dep]`.
+// The section can't go where the entry was, since it would take every entry after it along with
+// it, so it is written once the section the entry was in ends -- ahead of any comments written
+// against the header that follows, which describe that header rather than the new section. An
+// array of inline tables becomes `[[t.dep]]` sections in just the same way. Going the other way,
+// nothing may add to a table once it is written inline, so a section only becomes an inline table
+// when no header names anything within it.
+
+use crate::ast::*;
+use crate::configuration::ArrayOfTablesStyle;
+use crate::configuration::Configuration;
+use crate::parser::Span;
+use crate::sorting::attached_comments_start;
+use crate::sorting::is_fixed;
+use crate::sorting::section_content_end;
+use crate::sorting::section_end;
+
+/// Converts between inline tables and sections as the `inlineTable` and `arrayOfTables` options
+/// ask.
+pub fn apply_table_options(root: &mut Root, config: &Configuration) {
+  if config.inline_table_overflow_to_table {
+    split_off_entries(root, &|entry| is_overflowing_inline_table(entry, config));
+  }
+  if config.inline_table_collapse_tables {
+    collapse_into_inline_tables(root, config);
+  }
+  match config.array_of_tables {
+    ArrayOfTablesStyle::Maintain => {}
+    ArrayOfTablesStyle::Inline => inline_arrays_of_tables(root, config),
+    ArrayOfTablesStyle::Sections => split_off_entries(root, &is_array_of_inline_tables),
+  }
+}
+
+/// Splits off every entry `should_split` picks into a section of its own, along with any entry
+/// within it that `should_split` picks in turn. An entry it picks is always an inline table, which
+/// becomes a `[table]` section, or an array of them, each of which becomes a `[[table]]` section.
+/// An entry a `dprint-ignore` directive covers is left as it is.
+pub fn split_off_entries(root: &mut Root, should_split: &impl Fn(&Entry) -> bool) {
+  let mut items = Vec::with_capacity(root.items.len());
+  // the header of the section being walked, which the sections split off its entries go beneath
+  let mut header: Option<(Key, usize)> = None;
+  // the sections split off the current one, written once it ends so as not to take any of its
+  // remaining entries with them
+  let mut sections = Vec::new();
+  for item in std::mem::take(&mut root.items) {
+    match item {
+      RootItem::TableHeader(table_header) => {
+        header = Some((table_header.key.clone(), table_header.indent_in_source));
+        items.push(RootItem::TableHeader(table_header));
+        let at = attached_comments_start(&items, items.len() - 1);
+        items.splice(at..at, sections.drain(..));
+      }
+      RootItem::Entry(entry) if !entry.ignored && should_split(&entry) => {
+        // the comments written against the entry go with it
+        items.push(RootItem::Entry(entry));
+        let unit_start = attached_comments_start(&items, items.len() - 1);
+        let mut unit = items.drain(unit_start..).collect::<Vec<_>>();
+        let Some(RootItem::Entry(entry)) = unit.pop() else {
+          unreachable!("the unit ends with its entry");
+        };
+        let comments = unit
+          .into_iter()
+          .map(|item| match item {
+            RootItem::Comment(comment) => comment,
+            _ => unreachable!("only comments are attached to an entry"),
+          })
+          .collect();
+        let (parent, indent) = match &header {
+          Some((key, indent)) => (Some(key), *indent),
+          None => (None, 0),
+        };
+        match entry.value.kind {
+          ValueKind::Array(_) => split_off_array(parent, indent, comments, entry, &mut sections),
+          _ => split_off(parent, indent, comments, entry, false, should_split, &mut sections),
+        }
+      }
+      item => items.push(item),
+    }
+  }
+  items.append(&mut sections);
+  root.items = items;
+}
+
+/// Appends to `sections` a section made of `entry`, whose value is an inline table, named by
+/// `parent`'s key followed by the entry's own. The sections split off the new one follow it.
+fn split_off<'a>(
+  parent: Option<&Key<'a>>,
+  indent: usize,
+  comments: Vec<Comment<'a>>,
+  entry: Entry<'a>,
+  is_array_of_tables: bool,
+  should_split: &impl Fn(&Entry) -> bool,
+  sections: &mut Vec<RootItem<'a>>,
+) {
+  let ValueKind::InlineTable(table) = entry.value.kind else {
+    unreachable!("only an inline table is split off");
+  };
+  // the table's entries sit where its entry did
+  let body_indent = entry.indent_in_source;
+  let key = match parent {
+    Some(parent) => Key {
+      first: parent.first.clone(),
+      rest: parent.rest.iter().cloned().chain(entry.key.parts().cloned()).collect(),
+    },
+    None => entry.key,
+  };
+  // a new section is set apart from whatever it now follows
+  let has_comments = !comments.is_empty();
+  sections.extend(comments.into_iter().enumerate().map(|(i, mut comment)| {
+    comment.blank_line_before = i == 0;
+    comment.indent_in_source = indent;
+    RootItem::Comment(comment)
+  }));
+  let (trailing_comment, comment_below) = match (table.comment_after_open, entry.trailing_comment) {
+    (Some(open), after_close) => (Some(open), after_close),
+    (None, after_close) => (after_close, None),
+  };
+  sections.push(RootItem::TableHeader(TableHeader {
+    key: key.clone(),
+    is_array_of_tables,
+    blank_line_before: !has_comments,
+    trailing_comment,
+    indent_in_source: indent,
+    span: Span::default(),
+    ignored: false,
+  }));
+
+  let body = |mut comment: Comment<'a>| {
+    comment.indent_in_source = body_indent;
+    RootItem::Comment(comment)
+  };
+  sections.extend(comment_below.map(body));
+  let mut nested = Vec::new();
+  for (i, mut inner) in table.entries.into_iter().enumerate() {
+    let mut comments = std::mem::take(&mut inner.leading_comments);
+    if i == 0 {
+      inner.blank_line_before = false;
+      if let Some(first) = comments.first_mut() {
+        first.blank_line_before = false;
+      }
+    }
+    inner.indent_in_source = body_indent;
+    if should_split(&inner) {
+      split_off(Some(&key), indent, comments, inner, false, should_split, &mut nested);
+    } else {
+      sections.extend(comments.into_iter().map(body));
+      sections.push(RootItem::Entry(inner));
+    }
+  }
+  sections.extend(table.comments_before_close.into_iter().map(body));
+  sections.append(&mut nested);
+}
+
+/// Appends to `sections` a `[[table]]` section for each of the inline tables in `entry`'s value.
+/// The comments above the entry, and those inside the array's brackets that belong to no value, go
+/// above the first section or below the last. A value's own comments go with its section.
+fn split_off_array<'a>(parent: Option<&Key<'a>>, indent: usize, mut comments: Vec<Comment<'a>>, entry: Entry<'a>, sections: &mut Vec<RootItem<'a>>) {
+  let ValueKind::Array(array) = entry.value.kind else {
+    unreachable!("only an array of inline tables is split off");
+  };
+  comments.extend(array.comment_after_open);
+  comments.extend(entry.trailing_comment);
+  for value in array.values {
+    let element = Entry {
+      key: entry.key.clone(),
+      value: value.value,
+      blank_line_before: false,
+      trailing_comment: value.trailing_comment,
+      leading_comments: Vec::new(),
+      indent_in_source: entry.indent_in_source,
+      ignored: false,
+    };
+    let element_comments = std::mem::take(&mut comments).into_iter().chain(value.leading_comments).collect();
+    split_off(parent, indent, element_comments, element, true, &|_| false, sections);
+  }
+  sections.extend(array.comments_before_close.into_iter().map(|mut comment| {
+    comment.indent_in_source = entry.indent_in_source;
+    RootItem::Comment(comment)
+  }));
+}
+
+/// Whether an entry's value is an array holding nothing but inline tables, which
+/// `arrayOfTables: sections` splits off.
+fn is_array_of_inline_tables(entry: &Entry) -> bool {
+  match &entry.value.kind {
+    // a value a `dprint-ignore` directive covers has to stay as it is, so its array does too
+    ValueKind::Array(array) => {
+      !array.values.is_empty()
+        && array
+          .values
+          .iter()
+          .all(|value| !value.ignored && matches!(value.value.kind, ValueKind::InlineTable(_)))
+    }
+    _ => false,
+  }
+}
+
+/// Whether an entry whose value is an inline table written on a single line runs past the line
+/// width, so that `inlineTable.overflowToTable` splits it off. Indentation isn't counted, since
+/// how deep an entry is indented isn't known until it is generated.
+fn is_overflowing_inline_table(entry: &Entry, config: &Configuration) -> bool {
+  match &entry.value.kind {
+    ValueKind::InlineTable(table) if !table.force_use_new_lines(config) => {
+      let comment_width = entry.trailing_comment.as_ref().map(|comment| 1 + comment.text.chars().count()).unwrap_or(0);
+      entry_width(entry, config) + comment_width > config.line_width as usize
+    }
+    _ => false,
+  }
+}
+
+/// How wide `key = value` is when written on a single line.
+fn entry_width(entry: &Entry, config: &Configuration) -> usize {
+  let dot_width = if config.key_space_surrounding_dots { 3 } else { 1 };
+  let key_width = entry.key.parts().map(|part| part.text.chars().count()).sum::<usize>() + entry.key.rest.len() * dot_width;
+  let equals_width = if config.space_surrounding_equals { 3 } else { 1 };
+  key_width + equals_width + value_width(&entry.value, config)
+}
+
+fn value_width(value: &Value, config: &Configuration) -> usize {
+  // a collection with nothing in it is written without padding
+  let separated = |widths: &[usize], pad: bool| match widths.len() {
+    0 => 2,
+    len => 2 + widths.iter().sum::<usize>() + (len - 1) * 2 + if pad { 2 } else { 0 },
+  };
+  match &value.kind {
+    ValueKind::Scalar(text) => text.chars().count(),
+    // only the line the string starts on matters
+    ValueKind::MultiLineString(text) => text.lines().next().map(|line| line.chars().count()).unwrap_or(0),
+    ValueKind::Array(array) => {
+      let widths = array.values.iter().map(|item| value_width(&item.value, config)).collect::<Vec<_>>();
+      separated(&widths, config.array_space_surrounding_brackets)
+    }
+    ValueKind::InlineTable(table) => {
+      let widths = table.entries.iter().map(|entry| entry_width(entry, config)).collect::<Vec<_>>();
+      separated(&widths, config.inline_table_space_surrounding_braces)
+    }
+  }
+}
+
+// ---- collapsing ----
+
+/// Turns each subtable's section that is small enough into an inline table within the section of
+/// its nearest ancestor, working from the end of the file so that the table it is written into can
+/// go on to be collapsed in turn.
+fn collapse_into_inline_tables(root: &mut Root, config: &Configuration) {
+  let mut search_end = root.items.len();
+  while let Some(index) = root.items[..search_end].iter().rposition(RootItem::is_table_header) {
+    search_end = index;
+    if let Some((target, entry)) = collapsed_entry(&root.items, index, config) {
+      let insert_at = section_content_end(&root.items, target + 1);
+      root.items.drain(index..section_content_end(&root.items, index + 1));
+      root.items.insert(insert_at, RootItem::Entry(entry));
+      search_end = index + 1;
+    }
+  }
+}
+
+/// The entry the section whose header is at `index` collapses into, along with the index of the
+/// header of the section it goes in, or `None` when the section is to stay.
+///
+/// The section has to have no more entries than `inlineTable.collapseMaxEntries` allows, with no
+/// comments anywhere, since a single line has nowhere to keep them, and the entry it becomes has to
+/// fit on a single line.
+fn collapsed_entry<'a>(items: &[RootItem<'a>], index: usize, config: &Configuration) -> Option<(usize, Entry<'a>)> {
+  let RootItem::TableHeader(header) = &items[index] else {
+    unreachable!();
+  };
+  if header.is_array_of_tables || header.trailing_comment.is_some() || attached_comments_start(items, index) < index {
+    return None;
+  }
+  let body = &items[index + 1..section_content_end(items, index + 1)];
+  let entries = body
+    .iter()
+    .map(|item| match item {
+      RootItem::Entry(entry) if entry.trailing_comment.is_none() && !entry.value.is_known_multi_line(config, LineContext::default()) => Some(entry),
+      _ => None,
+    })
+    .collect::<Option<Vec<_>>>()?;
+  if entries.is_empty() || entries.len() > config.inline_table_collapse_max_entries as usize {
+    return None;
+  }
+  // nothing may add to an inline table, so no header may name a table within it
+  let names_table_within = items
+    .iter()
+    .any(|item| matches!(item, RootItem::TableHeader(other) if header.key.is_strict_prefix_of(&other.key)));
+  if names_table_within {
+    return None;
+  }
+  let target = enclosing_section(items, index)?;
+  if !can_move(items, index..section_content_end(items, index + 1), section_content_end(items, target + 1)) {
+    return None;
+  }
+
+  let mut parts = header.key.parts().skip(header_len(&items[target])).cloned();
+  let entry = Entry {
+    key: Key {
+      first: parts.next().expect("an ancestor's key is shorter"),
+      rest: parts.collect(),
+    },
+    value: Value {
+      kind: ValueKind::InlineTable(InlineTable {
+        entries: entries
+          .into_iter()
+          .map(|entry| Entry {
+            blank_line_before: false,
+            indent_in_source: 0,
+            ..entry.clone()
+          })
+          .collect(),
+        comment_after_open: None,
+        comments_before_close: Vec::new(),
+        multi_line_in_source: false,
+      }),
+      span: Span::default(),
+    },
+    blank_line_before: false,
+    trailing_comment: None,
+    leading_comments: Vec::new(),
+    indent_in_source: entries_indent(body).unwrap_or(header.indent_in_source),
+    ignored: false,
+  };
+  (entry_width(&entry, config) <= config.line_width as usize).then_some((target, entry))
+}
+
+fn entries_indent(items: &[RootItem]) -> Option<usize> {
+  items.iter().find_map(|item| match item {
+    RootItem::Entry(entry) => Some(entry.indent_in_source),
+    _ => None,
+  })
+}
+
+// ---- arrays of tables ----
+
+/// Turns each run of `[[table]]` sections into an array of inline tables within the section of the
+/// table they belong to, when each element fits on a line of its own.
+fn inline_arrays_of_tables(root: &mut Root, config: &Configuration) {
+  let mut search_start = 0;
+  while let Some(offset) = root.items[search_start..]
+    .iter()
+    .position(|item| matches!(item, RootItem::TableHeader(header) if header.is_array_of_tables))
+  {
+    let index = search_start + offset;
+    let run_end = array_of_tables_run_end(&root.items, index);
+    search_start = run_end;
+    let Some((owner, entry)) = inlined_array_of_tables(&root.items, index, run_end, config) else {
+      continue;
+    };
+    let insert_at = section_content_end(&root.items, owner.content_start());
+    let removed_start = attached_comments_start(&root.items, index);
+    root.items.drain(removed_start..run_end);
+    root.items.insert(insert_at, RootItem::Entry(entry));
+    search_start = removed_start + 1;
+  }
+}
+
+/// The index just past the content of the last of the `[[table]]` sections that follow one
+/// another from the one whose header is at `index`, all naming the same array.
+fn array_of_tables_run_end(items: &[RootItem], index: usize) -> usize {
+  let RootItem::TableHeader(header) = &items[index] else {
+    unreachable!();
+  };
+  let mut element = index;
+  loop {
+    let end = section_end(items, element + 1);
+    match items.get(end) {
+      Some(RootItem::TableHeader(next)) if next.is_array_of_tables && next.key.names_same_table(&header.key) => element = end,
+      _ => return section_content_end(items, element + 1),
+    }
+  }
+}
+
+/// The entry the `[[table]]` sections in `items[index..run_end]` become, along with the section it
+/// goes in, or `None` when they are to stay.
+///
+/// Every element of the array has to be there, with nothing else naming a table within it, since
+/// nothing may add to an inline table. An element's comments can only be kept where they sit
+/// outside of its braces: above its header, or beside it.
+fn inlined_array_of_tables<'a>(items: &[RootItem<'a>], index: usize, run_end: usize, config: &Configuration) -> Option<(Owner, Entry<'a>)> {
+  let RootItem::TableHeader(header) = &items[index] else {
+    unreachable!();
+  };
+  let is_elsewhere =
+    |item: &RootItem| matches!(item, RootItem::TableHeader(other) if header.key.is_strict_prefix_of(&other.key) || other.key.names_same_table(&header.key));
+  if items[..index].iter().any(is_elsewhere) || items[run_end..].iter().any(is_elsewhere) {
+    return None;
+  }
+  let owner = owner(items, index)?;
+  if !can_move(
+    items,
+    attached_comments_start(items, index)..run_end,
+    section_content_end(items, owner.content_start()),
+  ) {
+    return None;
+  }
+
+  let mut values = Vec::new();
+  // the comments gathered so far for the element whose header comes next
+  let mut comments = Vec::new();
+  for (i, item) in items.iter().enumerate().take(run_end).skip(attached_comments_start(items, index)) {
+    match item {
+      RootItem::Comment(comment) if i < index || comments_start(items, i) => comments.push(comment.clone()),
+      RootItem::TableHeader(element) => values.push(ArrayValue {
+        value: Value {
+          kind: ValueKind::InlineTable(InlineTable {
+            entries: Vec::new(),
+            comment_after_open: None,
+            comments_before_close: Vec::new(),
+            multi_line_in_source: false,
+          }),
+          span: Span::default(),
+        },
+        leading_comments: std::mem::take(&mut comments),
+        trailing_comment: element.trailing_comment.clone(),
+        blank_line_before: false,
+        ignored: false,
+      }),
+      RootItem::Entry(entry) if entry.trailing_comment.is_none() && !entry.value.is_known_multi_line(config, LineContext::default()) => {
+        let Some(ArrayValue {
+          value: Value {
+            kind: ValueKind::InlineTable(table),
+            ..
+          },
+          ..
+        }) = values.last_mut()
+        else {
+          unreachable!("an element's entries follow its header");
+        };
+        table.entries.push(Entry {
+          blank_line_before: false,
+          indent_in_source: 0,
+          ..entry.clone()
+        });
+      }
+      _ => return None,
+    }
+  }
+
+  // each element is written at least one level in, and followed by a comma
+  let element_width_limit = (config.line_width as usize).checked_sub(config.indent_width as usize + 1)?;
+  if values.iter().any(|value| value_width(&value.value, config) > element_width_limit) {
+    return None;
+  }
+  // a comment runs to the end of its line, so an array keeping one is written over several
+  let has_comments = values
+    .iter()
+    .any(|value| !value.leading_comments.is_empty() || value.trailing_comment.is_some());
+  let mut parts = header.key.parts().skip(owner.key_len(items)).cloned();
+  let entry = Entry {
+    key: Key {
+      first: parts.next().expect("an owner's key is shorter"),
+      rest: parts.collect(),
+    },
+    value: Value {
+      kind: ValueKind::Array(Array {
+        values,
+        comment_after_open: None,
+        comments_before_close: Vec::new(),
+        multi_line_in_source: has_comments,
+      }),
+      span: Span::default(),
+    },
+    blank_line_before: false,
+    trailing_comment: None,
+    leading_comments: Vec::new(),
+    indent_in_source: entries_indent(&items[index + 1..run_end]).unwrap_or(header.indent_in_source),
+    ignored: false,
+  };
+  Some((owner, entry))
+}
+
+/// Whether the comment at `index` is one of those written directly above the header that follows
+/// it, rather than one ending the section before.
+fn comments_start(items: &[RootItem], index: usize) -> bool {
+  let end = section_end(items, index);
+  end < items.len() && attached_comments_start(items, end) <= index
+}
+
+/// Whether `items[range]` may be moved to `insert_at`. Nothing a `dprint-ignore` directive covers is
+/// moved, and nothing is moved in directly after it either, which may be within the range it is part
+/// of.
+pub fn can_move(items: &[RootItem], range: std::ops::Range<usize>, insert_at: usize) -> bool {
+  !items[range].iter().any(is_fixed) && !insert_at.checked_sub(1).is_some_and(|previous| is_fixed(&items[previous]))
+}
+
+/// The index of the header of the section that the one whose header is at `index` belongs within,
+/// or `None` when it can't be written anywhere else. A section with no ancestor above it is a table
+/// in its own right rather than a subtable, and stays where it is.
+pub fn enclosing_section(items: &[RootItem], index: usize) -> Option<usize> {
+  match owner(items, index)? {
+    Owner::Root => None,
+    Owner::Header(target) => Some(target),
+  }
+}
+
+/// The section a table could be written within rather than under its own header.
+enum Owner {
+  /// The one above the first header.
+  Root,
+  /// The one whose header is at the given index.
+  Header(usize),
+}
+
+impl Owner {
+  /// The index the section's content starts at.
+  fn content_start(&self) -> usize {
+    match self {
+      Owner::Root => 0,
+      Owner::Header(index) => index + 1,
+    }
+  }
+
+  /// How many segments of a key the section's header already gives.
+  fn key_len(&self, items: &[RootItem]) -> usize {
+    match self {
+      Owner::Root => 0,
+      Owner::Header(index) => header_len(&items[*index]),
+    }
+  }
+}
+
+/// The section the table named by the header at `index` belongs within: that of its nearest
+/// ancestor above it, which is the element of an array of tables it belongs to if that is what it
+/// is, or the root when there is none. `None` when a header names a table between the two, since
+/// whatever the table turns into within the section would define that one instead.
+fn owner(items: &[RootItem], index: usize) -> Option<Owner> {
+  let RootItem::TableHeader(header) = &items[index] else {
+    unreachable!();
+  };
+  let is_ancestor = |item: &RootItem| matches!(item, RootItem::TableHeader(other) if other.key.is_strict_prefix_of(&header.key));
+  let owner = match items[..index].iter().rposition(is_ancestor) {
+    Some(target) => Owner::Header(target),
+    None => Owner::Root,
+  };
+  let owner_len = owner.key_len(items);
+  let names_table_between = items.iter().any(|item| is_ancestor(item) && header_len(item) > owner_len);
+  (!names_table_between).then_some(owner)
+}
+
+/// How many segments the key of the header `item` has.
+pub fn header_len(item: &RootItem) -> usize {
+  match item {
+    RootItem::TableHeader(header) => header.key.parts().count(),
+    _ => unreachable!("only a header names a table"),
+  }
+}
@@ -0,0 +1,295 @@
+// Semantic validation of a parsed document, for the `validateSemantics` option.
+//
+// The parser only delimits tokens, so a document it accepts may still define a key twice or open
+// the same table with two headers. This builds the logical key tree a TOML reader would -- tables
+// from headers, dotted keys and inline tables, and the elements of each array of tables -- and
+// reports each definition that conflicts with one before it.
+//
+// Only the shape of the tree matters here, so values are never looked into beyond the inline
+// tables within them, whose keys have to be unique as well.
+
+use std::borrow::Cow;
+use std::collections::hash_map;
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::parser::Span;
+use crate::parser::SyntaxError;
+use crate::strings;
+
+/// Checks that no key or table in `root` is defined more than once, reporting every definition
+/// that conflicts with an earlier one.
+pub fn validate(root: &Root) -> Result<(), Vec<SyntaxError>> {
+  let mut errors = Vec::new();
+  let mut document = Table::new(TableKind::Header, Span::new(0, 0));
+  // how many sections have been opened, which tells the dotted keys of one apart from another's
+  let mut sections = 0;
+  let mut section = 0;
+  // `None` while in a section whose header was in error, whose entries have no table to go in
+  let mut header: Option<Option<&TableHeader>> = Some(None);
+  for item in &root.items {
+    match item {
+      RootItem::TableHeader(table_header) => {
+        sections += 1;
+        section = sections;
+        header = match define_header(&mut document, table_header) {
+          Ok(()) => Some(Some(table_header)),
+          Err(error) => {
+            errors.push(error);
+            None
+          }
+        };
+      }
+      RootItem::Entry(entry) => {
+        if let Some(header) = header {
+          if let Err(error) = define_entry(section_table(&mut document, header), entry, section, &mut sections) {
+            errors.push(error);
+          }
+        }
+      }
+      RootItem::Comment(_) => {}
+    }
+  }
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+/// How a table came to be defined, which decides what may go on to add to it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+  /// Created only as the parent of a table named by a header, and still free to be defined.
+  Implicit,
+  /// Defined by a `[table]` header, or an element of an array of tables.
+  Header,
+  /// Defined by the dotted keys of the given section, within which they may go on adding to it.
+  Dotted(usize),
+  /// Written out in full as an inline table, so nothing may add to it afterwards.
+  Inline,
+}
+
+struct Table<'a> {
+  kind: TableKind,
+  /// The key segment that defined the table.
+  span: Span,
+  children: HashMap<Cow<'a, str>, Node<'a>>,
+}
+
+impl Table<'_> {
+  fn new(kind: TableKind, span: Span) -> Self {
+    Table {
+      kind,
+      span,
+      children: HashMap::new(),
+    }
+  }
+}
+
+enum Node<'a> {
+  Table(Table<'a>),
+  /// The elements of an array of tables, along with the segment of the header that began it.
+  ArrayOfTables(Vec<Table<'a>>, Span),
+  /// A value other than an inline table, which nothing may add to once written.
+  Value {
+    is_array: bool,
+    span: Span,
+  },
+}
+
+impl Node<'_> {
+  fn span(&self) -> Span {
+    match self {
+      Node::Table(table) => table.span,
+      Node::ArrayOfTables(_, span) | Node::Value { span, .. } => *span,
+    }
+  }
+}
+
+fn define_header<'a>(document: &mut Table<'a>, header: &'a TableHeader<'_>) -> Result<(), SyntaxError> {
+  let parts = header.key.parts().collect::<Vec<_>>();
+  let (last, parents) = parts.split_last().expect("a key has at least one segment");
+  let mut table = document;
+  for (i, part) in parents.iter().enumerate() {
+    table = match table.children.entry(name_of(part)) {
+      hash_map::Entry::Vacant(vacant) => match vacant.insert(Node::Table(Table::new(TableKind::Implicit, part.span))) {
+        Node::Table(table) => table,
+        _ => unreachable!(),
+      },
+      hash_map::Entry::Occupied(occupied) => {
+        let node = occupied.into_mut();
+        // a header may add a table to one defined by dotted keys, only not redefine it
+        let can_extend = match node {
+          Node::Table(table) => table.kind != TableKind::Inline,
+          Node::ArrayOfTables(..) => true,
+          Node::Value { .. } => false,
+        };
+        if !can_extend {
+          return Err(cannot_extend(&parts[..=i], node));
+        }
+        into_table(node)
+      }
+    };
+  }
+
+  let name = key_name(&parts);
+  match table.children.entry(name_of(last)) {
+    hash_map::Entry::Vacant(vacant) => {
+      vacant.insert(if header.is_array_of_tables {
+        Node::ArrayOfTables(vec![Table::new(TableKind::Header, last.span)], last.span)
+      } else {
+        Node::Table(Table::new(TableKind::Header, last.span))
+      });
+      Ok(())
+    }
+    hash_map::Entry::Occupied(occupied) => {
+      let node = occupied.into_mut();
+      let original = node.span();
+      let message = match (node, header.is_array_of_tables) {
+        (Node::Table(table), false) if table.kind == TableKind::Implicit => {
+          table.kind = TableKind::Header;
+          table.span = last.span;
+          return Ok(());
+        }
+        (Node::ArrayOfTables(elements, _), true) => {
+          elements.push(Table::new(TableKind::Header, last.span));
+          return Ok(());
+        }
+        (Node::Table(table), false) => match table.kind {
+          TableKind::Dotted(_) => format!("table `{name}` was already defined by dotted keys"),
+          TableKind::Inline => format!("table `{name}` was already defined as an inline table"),
+          _ => format!("table `{name}` is defined more than once"),
+        },
+        (Node::Table(_), true) => format!("`{name}` was already defined as a table rather than an array of tables"),
+        (Node::ArrayOfTables(..), false) => format!("`{name}` was already defined as an array of tables"),
+        (Node::Value { is_array: true, .. }, true) => format!("cannot append to `{name}`, which was defined as an array value"),
+        (Node::Value { .. }, _) => format!("`{name}` was already defined as a value"),
+      };
+      Err(conflict(message, last.span, original))
+    }
+  }
+}
+
+/// The name a key segment gives, with any escape in a basic string decoded, so that `"a"` and
+/// `"\u0061"` are the one key.
+fn name_of<'a>(part: &'a KeyPart<'_>) -> Cow<'a, str> {
+  strings::string_value(&part.text).unwrap_or(Cow::Borrowed(&part.text))
+}
+
+/// The table a section's entries are defined in, which is the last element of any array of tables
+/// along the way. The header has already been defined, so every segment of it is there to find.
+fn section_table<'t, 'a>(document: &'t mut Table<'a>, header: Option<&'a TableHeader<'_>>) -> &'t mut Table<'a> {
+  let mut table = document;
+  if let Some(header) = header {
+    for part in header.key.parts() {
+      let node = table.children.get_mut(&name_of(part));
+      table = into_table(node.expect("a section's header is defined before its entries"));
+    }
+  }
+  table
+}
+
+/// Defines an entry within `table`, creating a table for each segment of a dotted key before the
+/// last. `sections` counts the sections opened so far, and grows by one for each inline table
+/// within the value, whose dotted keys are its own.
+fn define_entry<'a>(table: &mut Table<'a>, entry: &'a Entry<'_>, section: usize, sections: &mut usize) -> Result<(), SyntaxError> {
+  let parts = entry.key.parts().collect::<Vec<_>>();
+  let (last, parents) = parts.split_last().expect("a key has at least one segment");
+  let mut table = table;
+  for (i, part) in parents.iter().enumerate() {
+    table = match table.children.entry(name_of(part)) {
+      hash_map::Entry::Vacant(vacant) => match vacant.insert(Node::Table(Table::new(TableKind::Dotted(section), part.span))) {
+        Node::Table(table) => table,
+        _ => unreachable!(),
+      },
+      hash_map::Entry::Occupied(occupied) => {
+        let node = occupied.into_mut();
+        match node {
+          // a table only a header's parent so far is defined by the first dotted key to reach it
+          Node::Table(table) if table.kind == TableKind::Implicit || table.kind == TableKind::Dotted(section) => {
+            table.kind = TableKind::Dotted(section);
+          }
+          Node::Table(existing) if existing.kind == TableKind::Header => {
+            let message = format!(
+              "dotted keys cannot add to table `{}`, which was already defined by a header",
+              key_name(&parts[..=i])
+            );
+            return Err(conflict(message, part.span, existing.span));
+          }
+          node => return Err(cannot_extend(&parts[..=i], node)),
+        }
+        into_table(node)
+      }
+    };
+  }
+
+  let node = value_node(&entry.value, last.span, sections)?;
+  match table.children.entry(name_of(last)) {
+    hash_map::Entry::Vacant(vacant) => {
+      vacant.insert(node);
+      Ok(())
+    }
+    hash_map::Entry::Occupied(occupied) => Err(conflict(format!("duplicate key `{}`", key_name(&parts)), last.span, occupied.get().span())),
+  }
+}
+
+/// The node a value defines, checking the keys of any inline table within it along the way.
+fn value_node<'a>(value: &'a Value<'_>, span: Span, sections: &mut usize) -> Result<Node<'a>, SyntaxError> {
+  match &value.kind {
+    ValueKind::InlineTable(inline_table) => {
+      *sections += 1;
+      let section = *sections;
+      let mut table = Table::new(TableKind::Inline, span);
+      for entry in &inline_table.entries {
+        define_entry(&mut table, entry, section, sections)?;
+      }
+      Ok(Node::Table(table))
+    }
+    ValueKind::Array(array) => {
+      for item in &array.values {
+        value_node(&item.value, span, sections)?;
+      }
+      Ok(Node::Value { is_array: true, span })
+    }
+    ValueKind::Scalar(_) | ValueKind::MultiLineString(_) => Ok(Node::Value { is_array: false, span }),
+  }
+}
+
+/// The table a key reaches through `node`, which is the latest element of an array of tables.
+fn into_table<'t, 'a>(node: &'t mut Node<'a>) -> &'t mut Table<'a> {
+  match node {
+    Node::Table(table) => table,
+    Node::ArrayOfTables(elements, _) => elements.last_mut().expect("an array of tables has at least one element"),
+    Node::Value { .. } => unreachable!("a value is never reached through"),
+  }
+}
+
+/// An error for a key that tries to reach into `node`, which cannot have anything added to it.
+fn cannot_extend(parts: &[&KeyPart], node: &Node) -> SyntaxError {
+  let name = key_name(parts);
+  let message = match node {
+    Node::Table(table) => match table.kind {
+      TableKind::Dotted(_) => format!("cannot add to `{name}`, which was defined by dotted keys under another header"),
+      _ => format!("cannot add to `{name}`, which was defined as an inline table"),
+    },
+    Node::ArrayOfTables(..) => format!("cannot add to `{name}`, which was defined as an array of tables"),
+    Node::Value { is_array: true, .. } => format!("cannot add to `{name}`, which was defined as an array value"),
+    Node::Value { .. } => format!("cannot add to `{name}`, which was defined as a value"),
+  };
+  let last = parts.last().expect("a key has at least one segment");
+  conflict(message, last.span, node.span())
+}
+
+fn conflict(message: String, span: Span, original: Span) -> SyntaxError {
+  SyntaxError {
+    message,
+    span,
+    original: Some(original),
+  }
+}
+
+/// The key as written, for naming it in a message.
+fn key_name(parts: &[&KeyPart]) -> String {
+  parts.iter().map(|part| part.text.as_ref()).collect::<Vec<_>>().join(".")
+}
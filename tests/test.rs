@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use dprint_plugin_toml::configuration::ConfigurationBuilder;
+use dprint_plugin_toml::configuration::TomlVersion;
 use dprint_plugin_toml::*;
 
 #[test]
@@ -66,3 +67,257 @@ fn should_report_parse_errors() {
     );
   }
 }
+
+#[test]
+fn should_report_semantic_errors_when_validating() {
+  let config = ConfigurationBuilder::new().validate_semantics(true).build();
+  let cases = [
+    ("a = 1\na = 2\n", "duplicate key `a`"),
+    ("a.b = 1\na.b = 2\n", "duplicate key `a.b`"),
+    ("a = { b = 1, b = 2 }\n", "duplicate key `b`"),
+    ("[a]\n[a]\n", "table `a` is defined more than once"),
+    ("[a]\nb = 1\n[a.b]\n", "`a.b` was already defined as a value"),
+    ("a.b = 1\n[a]\n", "table `a` was already defined by dotted keys"),
+    (
+      "[a.b]\n[a]\nb.c = 1\n",
+      "dotted keys cannot add to table `b`, which was already defined by a header",
+    ),
+    ("a = { b = 1 }\n[a.c]\n", "cannot add to `a`, which was defined as an inline table"),
+    ("a = { b = 1 }\n[a]\n", "table `a` was already defined as an inline table"),
+    ("a = []\n[[a]]\n", "cannot append to `a`, which was defined as an array value"),
+    ("[[a]]\n[a]\n", "`a` was already defined as an array of tables"),
+    ("[a]\n[[a]]\n", "`a` was already defined as a table rather than an array of tables"),
+    ("[[a]]\nb = 1\nb = 2\n", "duplicate key `b`"),
+    ("[[a.b]]\n[a]\nb.c = 1\n", "cannot add to `b`, which was defined as an array of tables"),
+    // keys are compared by name however they are spelt
+    ("\"a\" = 1\n\"\\u0061\" = 2\n", "duplicate key"),
+    ("[ab]\n['a\\u0062']\n[\"a\\u0062\"]\n", "is defined more than once"),
+  ];
+  for (input, expected) in cases {
+    let error = format_text(&PathBuf::from("file.toml"), input, &config).unwrap_err().to_string();
+    assert!(
+      error.contains(expected) && error.contains("first defined here"),
+      "for {input:?}\n  expected message containing {expected:?}\n  got {error}"
+    );
+  }
+
+  let valid = [
+    "[a.b]\n[a]\n",
+    "[a]\nb.c = 1\n[a.b.d]\n",
+    "[[a]]\nb = 1\n[[a]]\nb = 2\n[a.c]\n",
+    "a.b = 1\na.c = 2\n",
+    "\"a\" = 1\nb = { a = 1 }\n",
+  ];
+  for input in valid {
+    assert!(format_text(&PathBuf::from("file.toml"), input, &config).is_ok(), "for {input:?}");
+  }
+  // a duplicate is only an error when asked to look for one
+  let config = ConfigurationBuilder::new().build();
+  assert!(format_text(&PathBuf::from("file.toml"), "a = 1\na = 2\n", &config).is_ok());
+}
+
+#[test]
+fn should_expose_the_position_of_a_parse_error() {
+  let config = ConfigurationBuilder::new().build();
+  let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), "a = 1\nb = BAR\n", &config).unwrap_err();
+  assert_eq!(error.message(), "expected a value");
+  assert_eq!(error.span(), Span::new(10, 13));
+  assert_eq!((error.line(), error.column()), (2, 5));
+  assert!(error.to_string().starts_with("Line 2, column 5: expected a value"), "got {error}");
+
+  // the span is into the text that was passed in, and a byte order mark isn't a column
+  let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), "\u{FEFF}b = BAR\n", &config).unwrap_err();
+  assert_eq!(error.span(), Span::new(7, 10));
+  assert_eq!((error.line(), error.column()), (1, 5));
+
+  let config = ConfigurationBuilder::new().validate_semantics(true).build();
+  let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), "a = 1\na = 2\n", &config).unwrap_err();
+  assert_eq!(error.span(), Span::new(6, 7));
+  assert_eq!(error.original_span(), Some(Span::new(0, 1)));
+}
+
+#[test]
+fn should_report_every_parse_error_at_once() {
+  let config = ConfigurationBuilder::new().build();
+  let text = "a = BAR\nb = 1\nc = [1,\n  2 3,\n  [4, 5],\n]\nd = 2\n[e\n[f]\ng = \"unterminated\nh = 1 i = 2\n";
+  let error = format_text(&PathBuf::from("file.toml"), text, &config).unwrap_err();
+  let found = error.parse_errors().map(|error| (error.line(), error.message())).collect::<Vec<_>>();
+  assert_eq!(
+    found,
+    [
+      (1, "expected a value"),
+      // the rest of the array and the entry after it are passed over, up to the next header, which a
+      // nested array isn't taken for
+      (4, "expected ',' between array values"),
+      (8, "expected ']' to close the table header"),
+      (10, "unterminated string"),
+      (11, "expected a newline"),
+    ]
+  );
+  // displaying the error shows every one of them
+  assert_eq!(error.to_string().matches("Line ").count(), 5, "got {error}");
+
+  let config = ConfigurationBuilder::new().validate_semantics(true).build();
+  let error = format_text(
+    &PathBuf::from("file.toml"),
+    "a = 1\na = 2\n[b]\n[b]\nc = 1\nc = 2\n[d]\nd = 1\nd = 2\n",
+    &config,
+  )
+  .unwrap_err();
+  // the entries beneath a header in error have no table to be checked against
+  assert_eq!(error.parse_errors().map(|error| error.line()).collect::<Vec<_>>(), [2, 4, 9]);
+}
+
+#[test]
+fn should_pass_over_the_rest_of_a_multi_line_string_holding_an_error() {
+  let config = ConfigurationBuilder::new().build();
+  let cases = [
+    ("a = \"\"\"\n\\q\nb = 1\n\"\"\"\n", vec![(2, "invalid escape sequence `\\q`")]),
+    (
+      "a = '''\nx'''\nb = \"\"\"\\q\n[c]\n\\\"\"\"\"\" # d\ne = BAR\n",
+      vec![(3, "invalid escape sequence `\\q`"), (6, "expected a value")],
+    ),
+    // the array the string is in is still passed over up to the next header
+    (
+      "a = [\"\"\"\\q\n[b]\n\"\"\", 1,\n]\nc = 1\n[d]\ne = BAR\n",
+      vec![(1, "invalid escape sequence `\\q`"), (7, "expected a value")],
+    ),
+  ];
+  for (input, expected) in cases {
+    let error = format_text(&PathBuf::from("file.toml"), input, &config).unwrap_err();
+    let found = error.parse_errors().map(|error| (error.line(), error.message())).collect::<Vec<_>>();
+    assert_eq!(found, expected, "for {input:?}");
+  }
+}
+
+#[test]
+fn should_reject_invalid_numbers() {
+  let config = ConfigurationBuilder::new().build();
+  let cases = [
+    ("a = 1__2\n", "an underscore in a number must sit between two digits", "_"),
+    ("a = 1_\n", "an underscore in a number must sit between two digits", "_"),
+    ("a = 0x_1\n", "an underscore in a number must sit between two digits", "_"),
+    ("a = 0x\n", "expected a hexadecimal digit after `0x`", "0x"),
+    ("a = 0o8\n", "'8' is not an octal digit", "8"),
+    ("a = 0b102\n", "'2' is not a binary digit", "2"),
+    ("a = +0xFF\n", "a hexadecimal number cannot have a sign", "+"),
+    ("a = 0XFF\n", "the `0x` prefix must be lowercase", "0X"),
+    ("a = 01\n", "a decimal number cannot have leading zeros", "0"),
+    ("a = -01.5\n", "a decimal number cannot have leading zeros", "0"),
+    ("a = 1.e5\n", "expected a digit after the decimal point", "."),
+    ("a = 1.\n", "expected a digit after the decimal point", "."),
+    ("a = 1e\n", "expected a digit in the exponent", "e"),
+    ("a = 1e+\n", "expected a digit in the exponent", "e+"),
+    ("a = 1.5.2\n", "unexpected '.' in a number", "."),
+    ("a = 12ab\n", "unexpected 'a' in a number", "a"),
+    ("a = infinity\n", "expected a value", "infinity"),
+    ("a = +\n", "expected a value", "+"),
+    ("a = True\n", "expected a value", "True"),
+  ];
+  for (input, expected, highlighted) in cases {
+    let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), input, &config).unwrap_err();
+    let span = error.span();
+    assert_eq!((error.message(), &input[span.start..span.end]), (expected, highlighted), "for {input:?}");
+  }
+
+  let valid = "a = [0, -0, +0, 1_000, 0xdead_BEEF, 0o0_7, 0b1, 0.0, -0.5e-0_1, 1E6, 1e06, 6.626e-34, inf, -nan, true]\n";
+  assert!(format_text(&PathBuf::from("file.toml"), valid, &config).is_ok());
+}
+
+#[test]
+fn should_reject_invalid_date_times() {
+  let config = ConfigurationBuilder::new().build();
+  let cases = [
+    ("a = 2023-13-01\n", "the month must be between 01 and 12", "13"),
+    ("a = 2023-00-01\n", "the month must be between 01 and 12", "00"),
+    ("a = 2023-02-29\n", "the day must be between 01 and 28 for 2023-02", "29"),
+    ("a = 1900-02-29\n", "the day must be between 01 and 28 for 1900-02", "29"),
+    ("a = 2023-04-31\n", "the day must be between 01 and 30 for 2023-04", "31"),
+    ("a = 2023-01-00\n", "the day must be between 01 and 31 for 2023-01", "00"),
+    ("a = 2023-1-05\n", "expected a two digit month", "1-"),
+    ("a = 2023-01-05X10:00\n", "expected 'T' or a space between the date and the time", "X"),
+    ("a = 2023-01-05T25:00:00\n", "the hour must be between 00 and 23", "25"),
+    ("a = 2023-01-05T10:61:00Z\n", "the minute must be between 00 and 59", "61"),
+    ("a = 10:00:61\n", "the second must be between 00 and 60", "61"),
+    ("a = 10:00.5\n", "a time without seconds cannot have a fraction", "."),
+    ("a = 10:00:00.\n", "expected a digit after the decimal point", "."),
+    ("a = 10:00Z\n", "unexpected 'Z' in a date-time", "Z"),
+    ("a = 2023-01-05T10:00+24:00\n", "the offset's hour must be between 00 and 23", "24"),
+    ("a = 2023-01-05T10:00-01:60\n", "the offset's minute must be between 00 and 59", "60"),
+    ("a = 2023-01-05T10:00+0100\n", "expected ':' after the offset's hour", "0"),
+    ("a = 2023-01-05 10:00:00ZZ\n", "unexpected 'Z' in a date-time", "Z"),
+    ("a = 2023-01-05T10\n", "expected ':' after the hour", ""),
+  ];
+  for (input, expected, highlighted) in cases {
+    let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), input, &config).unwrap_err();
+    let span = error.span();
+    assert_eq!((error.message(), &input[span.start..span.end]), (expected, highlighted), "for {input:?}");
+  }
+
+  let valid = "a = [2024-02-29, 2000-02-29, 1979-05-27T07:32:00Z, 1979-05-27t07:32:00.999z, 1979-05-27 00:32:00-07:00, 1979-05-27T07:32, 23:59:60, 00:00]\n";
+  assert!(format_text(&PathBuf::from("file.toml"), valid, &config).is_ok());
+}
+
+#[test]
+fn should_reject_invalid_escapes() {
+  let config = ConfigurationBuilder::new().build();
+  let cases = [
+    ("a = \"\\q\"\n", "invalid escape sequence `\\q`", "\\q"),
+    ("a = \"x\\ y\"\n", "invalid escape sequence `\\ `", "\\ "),
+    ("a = \"\\uD800\"\n", "`\\uD800` is a surrogate, which a string cannot hold", "\\uD800"),
+    ("a = \"\\U00110000\"\n", "`\\U00110000` is beyond the last Unicode code point", "\\U00110000"),
+    ("a = \"\\U0001\"\n", "expected 8 hexadecimal digits in a `\\U` escape", "\\U0001"),
+    ("a = \"\\u12G4\"\n", "expected 4 hexadecimal digits in a `\\u` escape", "\\u12"),
+    ("a = \"\\x4\"\n", "expected 2 hexadecimal digits in a `\\x` escape", "\\x4"),
+    ("a = \"\"\"\n\\q\"\"\"\n", "invalid escape sequence `\\q`", "\\q"),
+    ("a = \"\"\"\n\\ x\"\"\"\n", "invalid escape sequence `\\ `", "\\ "),
+    ("a = { \"\\q\" = 1 }\n", "invalid escape sequence `\\q`", "\\q"),
+  ];
+  for (input, expected, highlighted) in cases {
+    let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), input, &config).unwrap_err();
+    let span = error.span();
+    assert_eq!((error.message(), &input[span.start..span.end]), (expected, highlighted), "for {input:?}");
+  }
+
+  let valid = "a = \"\\b\\t\\n\\f\\r\\\"\\\\\\e\\x7F\\u00E9\\U0001F600\\uFFFF\"\nb = \"\"\"\\  \n  c \\\n  \"\"\"\n";
+  assert!(format_text(&PathBuf::from("file.toml"), valid, &config).is_ok());
+}
+
+#[test]
+fn should_reject_toml_1_1_syntax_when_targeting_toml_1_0() {
+  let config = ConfigurationBuilder::new().toml_version(TomlVersion::V1_0).build();
+  let cases = [
+    ("a = {\n  b = 1 }\n", "a newline within an inline table requires TOML 1.1", "\n"),
+    ("a = { b = 1,\n  c = 2 }\n", "a newline within an inline table requires TOML 1.1", "\n"),
+    ("a = { # note\n  b = 1 }\n", "a comment within an inline table requires TOML 1.1", "#"),
+    ("a = { b = 1, }\n", "a trailing comma in an inline table requires TOML 1.1", ","),
+    ("a = \"\\e[\"\n", "the `\\e` escape requires TOML 1.1", "\\e"),
+    ("a = \"\\x41\"\n", "the `\\x` escape requires TOML 1.1", "\\x"),
+    ("a = \"\"\"\\x41\"\"\"\n", "the `\\x` escape requires TOML 1.1", "\\x"),
+    ("a = 07:32\n", "a time without seconds requires TOML 1.1", "07:32"),
+    ("a = 1979-05-27T07:32Z\n", "a time without seconds requires TOML 1.1", "07:32"),
+  ];
+  for (input, expected, highlighted) in cases {
+    let FormatError::Parse(error) = format_text(&PathBuf::from("file.toml"), input, &config).unwrap_err();
+    // the rest of a table broken over several lines isn't reported as well
+    assert!(error.others().is_empty(), "for {input:?}");
+    let span = error.span();
+    assert_eq!((error.message(), &input[span.start..span.end]), (expected, highlighted), "for {input:?}");
+  }
+}
+
+/// A file asking to be left alone is never parsed, so one that doesn't parse can still ask.
+#[test]
+fn should_leave_a_file_with_dprint_ignore_file_unparsed() {
+  let config = ConfigurationBuilder::new().build();
+  let text = format_text(&PathBuf::from("file.toml"), "# dprint-ignore-file\na = [1,\n", &config).unwrap();
+  assert_eq!(text, None);
+}
+
+#[test]
+fn should_keep_windows_newlines_out_of_an_ignored_entry() {
+  let config = ConfigurationBuilder::new().build();
+  let file_text = format_text(&PathBuf::from("file.toml"), "# dprint-ignore\na  =  [1,\r\n  2]  # c\r\nb=1\r\n", &config).unwrap();
+
+  assert_eq!(file_text.unwrap(), "# dprint-ignore\na  =  [1,\n  2]  # c\nb = 1\n");
+}